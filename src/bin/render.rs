@@ -0,0 +1,59 @@
+use std::{env, path::Path, process::exit};
+use artic_editor2::{cartridge::{Cartridge, write_atomic}, sfx::SFX_COUNT, music::TRACK_COUNT, synth::{render_sfx, render_track}, wav::{SAMPLE_RATE, encode_wav}};
+
+// headless audio rendering, no window needed:
+//   render <cart> sfx <id> [out.wav] [seconds]
+//   render <cart> track <id> [out.wav]
+
+fn usage() -> ! {
+    eprintln!("usage: render <cart> sfx <id> [out.wav] [seconds]");
+    eprintln!("       render <cart> track <id> [out.wav]");
+    exit(2)
+}
+
+fn main() {
+    let args : Vec<String> = env::args().collect();
+
+    if args.len() < 4 {
+        usage()
+    }
+
+    let (file_path, what) = (&args[1], args[2].as_str());
+
+    let Ok(id) = args[3].parse::<usize>() else {
+        usage()
+    };
+
+    let cart = match Cartridge::load(file_path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("could not read {} : {}", file_path, e);
+            exit(1)
+        }
+    };
+
+    let (waves, sounds) = (cart.waves(), cart.sounds());
+
+    let samples = match what {
+        "sfx" if id < SFX_COUNT => {
+            let seconds = args.get(5).and_then(|s| s.parse::<f32>().ok());
+
+            render_sfx(&waves, &sounds, id, seconds, SAMPLE_RATE)
+        },
+        "track" if id < TRACK_COUNT => render_track(&waves, &sounds, &cart.patterns(), &cart.music(), id, SAMPLE_RATE),
+        _ => usage(),
+    };
+
+    let out = match args.get(4) {
+        Some(o) => o.clone(),
+        None => format!("{}_{}{}.wav", Path::new(file_path).with_extension("").to_string_lossy(), what, id),
+    };
+
+    match write_atomic(Path::new(&out), &encode_wav(&samples, SAMPLE_RATE), false) {
+        Ok(()) => println!("wrote {} ({:.2}s)", out, samples.len() as f32 / SAMPLE_RATE as f32),
+        Err(e) => {
+            eprintln!("could not write {} : {}", out, e);
+            exit(1)
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use image::{ColorType, ImageEncoder, RgbaImage, codecs::png::PngEncoder, imageops::{FilterType, resize}};
+
+use crate::cartridge::CartError;
+
+// a plain 8 bit RGBA picture, the common ground between
+// image files and the editor's palette indexed sheets
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width : u32,
+    pub height : u32,
+    pub rgba : Vec<u8>,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32, fill: (u8, u8, u8, u8)) -> Bitmap {
+        let mut rgba : Vec<u8> = Vec::with_capacity((width * height * 4) as usize);
+
+        for _i in 0..width * height {
+            rgba.extend_from_slice(&[fill.0, fill.1, fill.2, fill.3]);
+        }
+
+        Bitmap { width, height, rgba }
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Bitmap, CartError> {
+        let img = image::load_from_memory(buf).map_err(|e| CartError::Image(e.to_string()))?.to_rgba8();
+
+        Ok(Bitmap {
+            width : img.width(),
+            height : img.height(),
+            rgba : img.into_raw(),
+        })
+    }
+
+    pub fn encode_png(&self) -> Result<Vec<u8>, CartError> {
+        let mut out : Vec<u8> = vec![];
+
+        PngEncoder::new(&mut out)
+            .write_image(&self.rgba, self.width, self.height, ColorType::Rgba8)
+            .map_err(|e| CartError::Image(e.to_string()))?;
+
+        Ok(out)
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8, u8) {
+        let at = ((x + y * self.width) * 4) as usize;
+
+        (self.rgba[at], self.rgba[at + 1], self.rgba[at + 2], self.rgba[at + 3])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, col: (u8, u8, u8, u8)) {
+        let at = ((x + y * self.width) * 4) as usize;
+
+        self.rgba[at..at + 4].copy_from_slice(&[col.0, col.1, col.2, col.3]);
+    }
+
+    // smooth rescale, used to fit pictures of any size to a fixed one
+
+    pub fn resized(&self, width: u32, height: u32) -> Bitmap {
+        if (width, height) == (self.width, self.height) {
+            return self.clone()
+        }
+
+        let src = RgbaImage::from_raw(self.width, self.height, self.rgba.clone()).expect("rgba buffer matches its size");
+
+        Bitmap { width, height, rgba : resize(&src, width, height, FilterType::Triangle).into_raw() }
+    }
+}
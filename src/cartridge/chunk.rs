@@ -0,0 +1,206 @@
+use std::fmt;
+
+// every chunk id a .tic file can contain
+//
+// ids marked "dep" are deprecated by TIC-80 but can still show up in old
+// carts, so they are kept around and written back untouched
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChunkType {
+    Dummy,
+    Tiles,
+    Sprites,
+    CoverDep,
+    Map,
+    Code,
+    Flags,
+    MapDep,
+    SoundDep,
+    Samples,
+    Waveform,
+    Temp,
+    Palette,
+    PatternsDep,
+    Music,
+    Patterns,
+    CodeZip,
+    Default,
+    Screen,
+    Binary,
+    Lang,
+    Reserved(u8),
+}
+
+impl ChunkType {
+    // the type only uses the low 5 bits of the header byte,
+    // the other 3 bits are the bank
+
+    pub fn from_id(id: u8) -> ChunkType {
+        match id & 0b00011111 {
+            0 => ChunkType::Dummy,
+            1 => ChunkType::Tiles,
+            2 => ChunkType::Sprites,
+            3 => ChunkType::CoverDep,
+            4 => ChunkType::Map,
+            5 => ChunkType::Code,
+            6 => ChunkType::Flags,
+            7 => ChunkType::MapDep,
+            8 => ChunkType::SoundDep,
+            9 => ChunkType::Samples,
+            10 => ChunkType::Waveform,
+            11 => ChunkType::Temp,
+            12 => ChunkType::Palette,
+            13 => ChunkType::PatternsDep,
+            14 => ChunkType::Music,
+            15 => ChunkType::Patterns,
+            16 => ChunkType::CodeZip,
+            17 => ChunkType::Default,
+            18 => ChunkType::Screen,
+            19 => ChunkType::Binary,
+            20 => ChunkType::Lang,
+            other => ChunkType::Reserved(other),
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            ChunkType::Dummy => 0,
+            ChunkType::Tiles => 1,
+            ChunkType::Sprites => 2,
+            ChunkType::CoverDep => 3,
+            ChunkType::Map => 4,
+            ChunkType::Code => 5,
+            ChunkType::Flags => 6,
+            ChunkType::MapDep => 7,
+            ChunkType::SoundDep => 8,
+            ChunkType::Samples => 9,
+            ChunkType::Waveform => 10,
+            ChunkType::Temp => 11,
+            ChunkType::Palette => 12,
+            ChunkType::PatternsDep => 13,
+            ChunkType::Music => 14,
+            ChunkType::Patterns => 15,
+            ChunkType::CodeZip => 16,
+            ChunkType::Default => 17,
+            ChunkType::Screen => 18,
+            ChunkType::Binary => 19,
+            ChunkType::Lang => 20,
+            ChunkType::Reserved(id) => id & 0b00011111,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChunkType::Dummy => "Dummy",
+            ChunkType::Tiles => "Tiles",
+            ChunkType::Sprites => "Sprites",
+            ChunkType::CoverDep => "Cover (dep)",
+            ChunkType::Map => "Map",
+            ChunkType::Code => "Code",
+            ChunkType::Flags => "Flags",
+            ChunkType::MapDep => "Map (dep)",
+            ChunkType::SoundDep => "Sound (dep)",
+            ChunkType::Samples => "Samples",
+            ChunkType::Waveform => "Waveform",
+            ChunkType::Temp => "Temp (dep)",
+            ChunkType::Palette => "Palette",
+            ChunkType::PatternsDep => "Patterns (dep)",
+            ChunkType::Music => "Music",
+            ChunkType::Patterns => "Patterns",
+            ChunkType::CodeZip => "Code (zip)",
+            ChunkType::Default => "Default",
+            ChunkType::Screen => "Screen",
+            ChunkType::Binary => "Binary",
+            ChunkType::Lang => "Lang",
+            ChunkType::Reserved(_) => "(Reserved)",
+        }
+    }
+}
+
+impl ChunkType {
+    // code and binary banks can be a full 64 KiB, which doesn't fit
+    // in the 16 bit size field, so TIC-80 stores that as a size of 0
+
+    pub fn zero_means_full(self) -> bool {
+        matches!(self, ChunkType::Code | ChunkType::Binary)
+    }
+
+    pub fn max_size(self) -> usize {
+        match self.zero_means_full() {
+            true => BANK_SIZE,
+            false => BANK_SIZE - 1,
+        }
+    }
+}
+
+impl fmt::Display for ChunkType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+pub const MAX_BANKS : u8 = 8;
+pub const BANK_SIZE : usize = 65536;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    // 0..=7, the top 3 bits of the header byte
+    pub bank : u8,
+    pub data : Vec<u8>,
+    pub kind : ChunkType,
+}
+
+impl Chunk {
+    pub fn new(kind: ChunkType, bank: u8, data: Vec<u8>) -> Chunk {
+        Chunk {
+            bank,
+            data,
+            kind,
+        }
+    }
+
+    pub fn is(&self, kind: ChunkType, bank: u8) -> bool {
+        self.kind == kind && self.bank == bank
+    }
+}
+
+pub fn extract(from: &[Chunk], kind: ChunkType, bank: u8) -> Chunk {
+    for i in from {
+        if i.is(kind, bank) {
+            return i.clone()
+        }
+    }
+
+    Chunk::new(kind, bank, vec![])
+}
+
+// swaps out the chunk with the same type and bank, or appends it
+
+pub fn replace(from: Vec<Chunk>, what: Chunk) -> Vec<Chunk> {
+    let mut new : Vec<Chunk> = vec![];
+
+    let mut added : bool = false;
+
+    for i in from {
+        if i.is(what.kind, what.bank) && !added {
+            new.push(what.clone());
+            added = true;
+        } else {
+            new.push(i);
+        }
+    }
+
+    if !added {
+        new.push(what);
+    }
+
+    new
+}
+
+pub fn find(from: &[Chunk], kind: ChunkType) -> bool {
+    from.iter().any(|i| i.kind == kind)
+}
+
+pub fn find_bank(from: &[Chunk], kind: ChunkType, bank: u8) -> bool {
+    from.iter().any(|i| i.is(kind, bank))
+}
@@ -0,0 +1,148 @@
+use std::{fs, io::{Read, Write}, path::{Path, PathBuf}};
+
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+
+use crate::bitmap::Bitmap;
+
+use super::chunk::{Chunk, ChunkType, BANK_SIZE, MAX_BANKS};
+use super::error::CartError;
+use super::png::{is_png, is_png_path, read_png_cart, write_png_cart};
+use super::reader::ChunkReader;
+
+pub fn parse_tic(buf: &[u8]) -> Result<Vec<Chunk>, CartError> {
+    ChunkReader::new(buf).map(|i| i.map(|c| c.to_chunk())).collect()
+}
+
+// what the 16 bit size field in a header actually means
+
+pub fn read_size(kind: ChunkType, size: usize) -> usize {
+    match size == 0 && kind.zero_means_full() {
+        true => BANK_SIZE,
+        false => size,
+    }
+}
+
+// the opposite of read_size, refusing sizes that would read back differently
+
+pub fn size_field(chunk: &Chunk) -> Result<u16, CartError> {
+    let len = chunk.data.len();
+
+    let fits = match chunk.kind.zero_means_full() {
+        true => len > 0 && len <= BANK_SIZE,
+        false => len < BANK_SIZE,
+    };
+
+    if !fits {
+        return Err(CartError::BadSize(chunk.kind, chunk.bank, len))
+    }
+
+    Ok((len % BANK_SIZE) as u16)
+}
+
+pub fn write_tic(from: &[Chunk]) -> Result<Vec<u8>, CartError> {
+    let mut bytes : Vec<u8> = vec![];
+
+    for i in from {
+        let type_id = i.kind.id();
+
+        let size = size_field(i)?;
+
+        let size_low : u8 = (size & 0b0000000011111111) as u8;
+        let size_high : u8 = ((size & 0b1111111100000000) >> 8) as u8;
+
+        if i.bank >= MAX_BANKS {
+            return Err(CartError::InvalidBank(i.kind, i.bank))
+        }
+
+        bytes.push((i.bank << 5) | type_id);
+        bytes.push(size_low);
+        bytes.push(size_high);
+        bytes.push(0);
+
+        bytes.extend_from_slice(&i.data);
+    }
+
+    Ok(bytes)
+}
+
+// "Code (zip)" chunks hold the whole program as a zlib stream
+
+pub fn unzip(data: &[u8]) -> Result<Vec<u8>, CartError> {
+    let mut out : Vec<u8> = vec![];
+
+    ZlibDecoder::new(data).read_to_end(&mut out).map_err(CartError::Zip)?;
+
+    Ok(out)
+}
+
+pub fn zip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+
+    // writing into a Vec can't fail
+    encoder.write_all(data).expect("zlib into memory");
+    encoder.finish().expect("zlib into memory")
+}
+
+// .png carts are read and written transparently, based on the
+// file contents when loading and on the extension when saving
+
+pub fn deconstruct_tic(path: &str) -> Result<Vec<Chunk>, CartError> {
+    let buf = fs::read(path)?;
+
+    match is_png(&buf) {
+        true => parse_tic(&read_png_cart(&buf)?.0),
+        false => parse_tic(&buf),
+    }
+}
+
+pub fn encode_for(path: &str, from: &[Chunk], cover: Option<&Bitmap>) -> Result<Vec<u8>, CartError> {
+    let bytes = write_tic(from)?;
+
+    match is_png_path(path) {
+        true => write_png_cart(&bytes, cover),
+        false => Ok(bytes),
+    }
+}
+
+pub fn construct_tic(path: &str, from: &[Chunk]) -> Result<(), CartError> {
+    write_atomic(Path::new(path), &encode_for(path, from, None)?, false)
+}
+
+pub fn construct_tic_with_backup(path: &str, from: &[Chunk]) -> Result<(), CartError> {
+    write_atomic(Path::new(path), &encode_for(path, from, None)?, true)
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+
+    path.with_file_name(name)
+}
+
+// the new file is written next to the old one and renamed over it,
+// so a crash mid-save never leaves a half written (or stale tailed) cart
+
+pub fn write_atomic(path: &Path, bytes: &[u8], backup: bool) -> Result<(), CartError> {
+    let tmp = sibling(path, &format!(".{}.tmp", std::process::id()));
+
+    let written = (|| -> Result<(), CartError> {
+        let mut file = fs::OpenOptions::new().create(true).write(true).truncate(true).open(&tmp)?;
+
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        if backup && path.exists() {
+            fs::copy(path, sibling(path, ".bak"))?;
+        }
+
+        fs::rename(&tmp, path)?;
+
+        Ok(())
+    })();
+
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+
+    written
+}
@@ -0,0 +1,55 @@
+use std::{fmt, io};
+
+use super::chunk::ChunkType;
+
+#[derive(Debug)]
+pub enum CartError {
+    Io(io::Error),
+    InvalidBank(ChunkType, u8),
+    Zip(io::Error),
+    BadSize(ChunkType, u8, usize),
+    TruncatedHeader { offset : usize, left : usize },
+    Overrun { offset : usize, kind : ChunkType, size : usize, left : usize },
+    UnknownId { offset : usize, id : u8 },
+    Image(String),
+    NoCartInPng,
+    CoverTooSmall { needed : usize, room : usize },
+    CodeTooLarge(usize),
+    CodeNotUtf8 { offset : usize },
+    BadPalette { line : usize, text : String },
+}
+
+impl fmt::Display for CartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartError::Io(e) => write!(f, "i/o error: {}", e),
+            CartError::InvalidBank(kind, bank) => write!(f, "{} chunk has bank {}, only 0-7 fit in a .tic", kind, bank),
+            CartError::Zip(e) => write!(f, "compressed code is corrupt: {}", e),
+            CartError::BadSize(kind, bank, len) => write!(f, "{} chunk in bank {} can't hold {} bytes ({}-{} allowed)", kind, bank, len, kind.zero_means_full() as usize, kind.max_size()),
+            CartError::TruncatedHeader { offset, left } => write!(f, "chunk header at byte {} is cut short ({} of 4 bytes)", offset, left),
+            CartError::Overrun { offset, kind, size, left } => write!(f, "{} chunk at byte {} claims {} bytes but only {} are left", kind, offset, size, left),
+            CartError::UnknownId { offset, id } => write!(f, "unknown chunk id {} at byte {}", id, offset),
+            CartError::Image(e) => write!(f, "image error: {}", e),
+            CartError::NoCartInPng => write!(f, "this png doesn't contain a cart"),
+            CartError::BadPalette { line, text } => write!(f, "palette file line {} isn't a colour: {:?}", line, text),
+            CartError::CodeTooLarge(len) => write!(f, "code is {} bytes, only 8 banks of 64 KiB fit in a cart", len),
+            CartError::CodeNotUtf8 { offset } => write!(f, "code isn't utf-8 (byte {}), editing it would change it", offset),
+            CartError::CoverTooSmall { needed, room } => write!(f, "cover image is too small ({} bytes needed, {} available)", needed, room),
+        }
+    }
+}
+
+impl std::error::Error for CartError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartError::Io(e) | CartError::Zip(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartError {
+    fn from(e: io::Error) -> CartError {
+        CartError::Io(e)
+    }
+}
@@ -0,0 +1,466 @@
+mod chunk;
+mod codec;
+mod error;
+mod png;
+mod reader;
+
+use std::{fs, path::Path};
+
+use crate::bitmap::Bitmap;
+use crate::flags::Flags;
+use crate::map::Map;
+use crate::screen::Screen;
+use crate::music::{Music, Patterns};
+use crate::sfx::Sounds;
+use crate::wave::Waves;
+
+pub use chunk::{Chunk, ChunkType, BANK_SIZE, MAX_BANKS, extract, replace, find, find_bank};
+pub use codec::{parse_tic, write_tic, read_size, size_field, deconstruct_tic, construct_tic, construct_tic_with_backup, encode_for, write_atomic, zip, unzip};
+pub use png::{is_png, is_png_path, read_png_cart, write_png_cart, hide, unhide, default_cover};
+pub use error::CartError;
+pub use reader::{ChunkReader, ChunkRef, HEADER_SIZE};
+
+pub const DEFAULT_PALETTE : [u8; 48] = [26, 28, 44, 93, 39, 93, 177, 62, 83, 239, 125, 87, 255, 205, 117, 167, 240, 112, 56, 183, 100, 37, 113, 121, 41, 54, 111, 59, 93, 201, 65, 166, 246, 115, 239, 247, 244, 244, 244, 148, 176, 194, 86, 108, 134, 51, 60, 87];
+
+// the first two waves TIC-80 gives a cart with a Default chunk, the rest stay flat
+pub const DEFAULT_WAVEFORMS : [u8; 32] = [0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe, 0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23, 0x01];
+pub const SPR_PIXELS : usize = 64;
+
+// a whole .tic file, kept as its list of chunks so that anything
+// the editor doesn't understand is written back exactly as it was read
+// (carts loaded from a .png also keep the picture they came in)
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cartridge {
+    chunks : Vec<Chunk>,
+    cover : Option<Bitmap>,
+}
+
+impl Cartridge {
+    pub fn new() -> Cartridge {
+        Cartridge { chunks : vec![], cover : None }
+    }
+
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Cartridge {
+        Cartridge { chunks, cover : None }
+    }
+
+    // accepts both .tic and .png cart contents
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Cartridge, CartError> {
+        if !is_png(buf) {
+            return Ok(Cartridge::from_chunks(parse_tic(buf)?))
+        }
+
+        let (tic, cover) = read_png_cart(buf)?;
+
+        let mut cart = Cartridge::from_chunks(parse_tic(&tic)?);
+        cart.cover = Some(cover);
+
+        Ok(cart)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CartError> {
+        write_tic(&self.chunks)
+    }
+
+    pub fn load(path: &str) -> Result<Cartridge, CartError> {
+        Cartridge::from_bytes(&fs::read(path)?)
+    }
+
+    // a .png path writes a png cart, anything else a plain .tic
+
+    pub fn save(&self, path: &str) -> Result<(), CartError> {
+        write_atomic(Path::new(path), &encode_for(path, &self.chunks, self.cover.as_ref())?, false)
+    }
+
+    // same as save, but the previous file is kept as <path>.bak
+
+    pub fn save_with_backup(&self, path: &str) -> Result<(), CartError> {
+        write_atomic(Path::new(path), &encode_for(path, &self.chunks, self.cover.as_ref())?, true)
+    }
+
+    pub fn cover(&self) -> Option<&Bitmap> {
+        self.cover.as_ref()
+    }
+
+    pub fn set_cover(&mut self, cover: Option<Bitmap>) {
+        self.cover = cover;
+    }
+
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    pub fn get(&self, kind: ChunkType, bank: u8) -> Option<&Chunk> {
+        self.chunks.iter().find(|i| i.is(kind, bank))
+    }
+
+    pub fn has(&self, kind: ChunkType) -> bool {
+        find(&self.chunks, kind)
+    }
+
+    // every bank that has a chunk of this type, in file order
+
+    pub fn banks(&self, kind: ChunkType) -> Vec<u8> {
+        self.chunks.iter().filter(|i| i.kind == kind).map(|i| i.bank).collect()
+    }
+
+    // replaces the chunk with the same type and bank, or appends it
+
+    pub fn set(&mut self, what: Chunk) {
+        self.chunks = replace(std::mem::take(&mut self.chunks), what);
+    }
+
+    pub fn remove(&mut self, kind: ChunkType, bank: u8) {
+        self.chunks.retain(|i| !i.is(kind, bank));
+    }
+
+    fn data(&self, kind: ChunkType) -> &[u8] {
+        match self.get(kind, 0) {
+            Some(c) => &c.data,
+            None => &[],
+        }
+    }
+
+    // typed access
+
+    // the chunk holds the screen (SCN) palette, then the overlay (OVR) one
+
+    pub fn palette(&self) -> Vec<(u8, u8, u8)> {
+        self.palette_at(0)
+    }
+
+    pub fn ovr_palette(&self) -> Vec<(u8, u8, u8)> {
+        self.palette_at(1)
+    }
+
+    fn palette_at(&self, which: usize) -> Vec<(u8, u8, u8)> {
+        let size = DEFAULT_PALETTE.len();
+
+        let mut raw = match self.get(ChunkType::Palette, 0) {
+            Some(c) => c.data.iter().skip(which * size).take(size).copied().collect(),
+            None if self.has(ChunkType::Default) => DEFAULT_PALETTE.to_vec(),
+            None => vec![],
+        };
+
+        // TIC-80 drops trailing zeros, so a black last colour makes the chunk shorter
+
+        raw.resize(size, 0);
+
+        raw.chunks(3).map(|c| (c[0], c[1], c[2])).collect()
+    }
+
+    pub fn set_palette(&mut self, palette: &[(u8, u8, u8)]) {
+        self.set_palette_at(0, palette);
+    }
+
+    pub fn set_ovr_palette(&mut self, palette: &[(u8, u8, u8)]) {
+        self.set_palette_at(1, palette);
+    }
+
+    // only one of the two is replaced, the other keeps its bytes
+
+    fn set_palette_at(&mut self, which: usize, palette: &[(u8, u8, u8)]) {
+        let size = DEFAULT_PALETTE.len();
+
+        let mut data = match self.get(ChunkType::Palette, 0) {
+            Some(c) => c.data.clone(),
+            None if self.has(ChunkType::Default) => [expand(&self.palette()), expand(&self.ovr_palette())].concat(),
+            None => vec![],
+        };
+
+        if data.len() < (which + 1) * size {
+            data.resize((which + 1) * size, 0);
+        }
+
+        let mut colours = expand(palette);
+        colours.resize(size, 0);
+
+        data[which * size..(which + 1) * size].copy_from_slice(&colours);
+
+        self.set(Chunk::new(ChunkType::Palette, 0, data));
+    }
+
+    // bank 0 is what TIC-80 shows unless the game syncs another one in
+
+    pub fn tiles(&self) -> Vec<Vec<u8>> {
+        self.tiles_in(0)
+    }
+
+    pub fn set_tiles(&mut self, tiles: &[Vec<u8>]) {
+        self.set_tiles_in(0, tiles);
+    }
+
+    pub fn sprites(&self) -> Vec<Vec<u8>> {
+        self.sprites_in(0)
+    }
+
+    pub fn set_sprites(&mut self, sprites: &[Vec<u8>]) {
+        self.set_sprites_in(0, sprites);
+    }
+
+    pub fn tiles_in(&self, bank: u8) -> Vec<Vec<u8>> {
+        self.get(ChunkType::Tiles, bank).map_or(vec![], |c| unpack_sheet(&c.data))
+    }
+
+    pub fn set_tiles_in(&mut self, bank: u8, tiles: &[Vec<u8>]) {
+        let data = pack_sheet(tiles);
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Tiles, bank),
+            false => self.set(Chunk::new(ChunkType::Tiles, bank, data)),
+        }
+    }
+
+    pub fn sprites_in(&self, bank: u8) -> Vec<Vec<u8>> {
+        self.get(ChunkType::Sprites, bank).map_or(vec![], |c| unpack_sheet(&c.data))
+    }
+
+    pub fn set_sprites_in(&mut self, bank: u8, sprites: &[Vec<u8>]) {
+        let data = pack_sheet(sprites);
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Sprites, bank),
+            false => self.set(Chunk::new(ChunkType::Sprites, bank, data)),
+        }
+    }
+
+    // like the sheets, every bank has its own map and flags
+
+    pub fn map(&self) -> Map {
+        self.map_in(0)
+    }
+
+    pub fn set_map(&mut self, map: &Map) {
+        self.set_map_in(0, map);
+    }
+
+    pub fn map_in(&self, bank: u8) -> Map {
+        self.get(ChunkType::Map, bank).map_or(Map::default(), |c| Map::from_chunk(&c.data))
+    }
+
+    pub fn set_map_in(&mut self, bank: u8, map: &Map) {
+        let data = map.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Map, bank),
+            false => self.set(Chunk::new(ChunkType::Map, bank, data)),
+        }
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags_in(0)
+    }
+
+    pub fn set_flags(&mut self, flags: &Flags) {
+        self.set_flags_in(0, flags);
+    }
+
+    pub fn flags_in(&self, bank: u8) -> Flags {
+        self.get(ChunkType::Flags, bank).map_or(Flags::default(), |c| Flags::from_chunk(&c.data))
+    }
+
+    pub fn set_flags_in(&mut self, bank: u8, flags: &Flags) {
+        let data = flags.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Flags, bank),
+            false => self.set(Chunk::new(ChunkType::Flags, bank, data)),
+        }
+    }
+
+    pub fn waves(&self) -> Waves {
+        match self.get(ChunkType::Waveform, 0) {
+            Some(c) => Waves::from_chunk(&c.data),
+            None if self.has(ChunkType::Default) => Waves::from_chunk(&DEFAULT_WAVEFORMS),
+            None => Waves::default(),
+        }
+    }
+
+    pub fn set_waves(&mut self, waves: &Waves) {
+        let data = waves.to_chunk();
+
+        // with a Default chunk no Waveform chunk means the default waves,
+        // so flat ones have to be written out
+
+        match data.is_empty() && !self.has(ChunkType::Default) {
+            true => self.remove(ChunkType::Waveform, 0),
+            false => self.set(Chunk::new(ChunkType::Waveform, 0, data)),
+        }
+    }
+
+    pub fn sounds(&self) -> Sounds {
+        Sounds::from_chunk(self.data(ChunkType::Samples))
+    }
+
+    pub fn set_sounds(&mut self, sounds: &Sounds) {
+        let data = sounds.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Samples, 0),
+            false => self.set(Chunk::new(ChunkType::Samples, 0, data)),
+        }
+    }
+
+    pub fn patterns(&self) -> Patterns {
+        Patterns::from_chunk(self.data(ChunkType::Patterns))
+    }
+
+    pub fn set_patterns(&mut self, patterns: &Patterns) {
+        let data = patterns.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Patterns, 0),
+            false => self.set(Chunk::new(ChunkType::Patterns, 0, data)),
+        }
+    }
+
+    pub fn music(&self) -> Music {
+        Music::from_chunk(self.data(ChunkType::Music))
+    }
+
+    pub fn set_music(&mut self, music: &Music) {
+        let data = music.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Music, 0),
+            false => self.set(Chunk::new(ChunkType::Music, 0, data)),
+        }
+    }
+
+    pub fn screen(&self) -> Screen {
+        Screen::from_chunk(self.data(ChunkType::Screen))
+    }
+
+    pub fn set_screen(&mut self, screen: &Screen) {
+        let data = screen.to_chunk();
+
+        match data.is_empty() {
+            true => self.remove(ChunkType::Screen, 0),
+            false => self.set(Chunk::new(ChunkType::Screen, 0, data)),
+        }
+    }
+
+    // compressed code wins over plain code, like in TIC-80;
+    // big programs are spread over the code banks in bank order
+
+    pub fn code(&self) -> Result<String, CartError> {
+        let raw = match self.get(ChunkType::CodeZip, 0) {
+            Some(c) => unzip(&c.data)?,
+            None => {
+                let mut raw : Vec<u8> = vec![];
+
+                // TIC-80 puts the start of the code in the highest bank used
+
+                for bank in (0..MAX_BANKS).rev() {
+                    if let Some(c) = self.get(ChunkType::Code, bank) {
+                        raw.extend_from_slice(&c.data);
+                    }
+                }
+
+                raw
+            }
+        };
+
+        // other encodings can't go through the editor unchanged, so they are refused
+
+        String::from_utf8(raw).map_err(|e| CartError::CodeNotUtf8 { offset : e.utf8_error().valid_up_to() })
+    }
+
+    // the zip chunk is only rewritten when the code is set,
+    // so loading and saving a compressed cart keeps its exact bytes
+    // (an empty code chunk can't be stored, so it's dropped instead)
+
+    pub fn set_code(&mut self, code: &str) -> Result<(), CartError> {
+        if self.has(ChunkType::CodeZip) {
+            self.set(Chunk::new(ChunkType::CodeZip, 0, zip(code.as_bytes())));
+            return Ok(())
+        }
+
+        let banks : Vec<&[u8]> = code.as_bytes().chunks(BANK_SIZE).collect();
+
+        if banks.len() > MAX_BANKS as usize {
+            return Err(CartError::CodeTooLarge(code.len()))
+        }
+
+        // the first slice goes in the highest bank, bank 0 gets the tail
+
+        for bank in 0..MAX_BANKS {
+            match (bank as usize) < banks.len() {
+                true => self.set(Chunk::new(ChunkType::Code, bank, banks[banks.len() - 1 - bank as usize].to_vec())),
+                false => self.remove(ChunkType::Code, bank),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// 4bpp sheets store two pixels per byte, low nibble first
+
+pub fn unpack_sheet(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut sheet : Vec<Vec<u8>> = vec![];
+    let mut cur : Vec<u8> = vec![];
+
+    for k in data {
+        cur.push(k & 0b00001111);
+        cur.push((k & 0b11110000) >> 4);
+
+        if cur.len() == SPR_PIXELS {
+            sheet.push(cur.clone());
+            cur.clear();
+        }
+    }
+
+    // trailing zeros get trimmed on save, so the last sprite may be short
+
+    if !cur.is_empty() {
+        cur.resize(SPR_PIXELS, 0);
+        sheet.push(cur);
+    }
+
+    sheet
+}
+
+// trailing zeros are trimmed the way TIC-80 saves them
+
+pub fn pack_sheet(sheet: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = compress(&flatten(sheet));
+    let used = data.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+    data.truncate(used);
+
+    data
+}
+
+pub fn flatten(thick: &[Vec<u8>]) -> Vec<u8> {
+    thick.iter().flatten().copied().collect()
+}
+
+pub fn compress(wide: &[u8]) -> Vec<u8> {
+    wide.chunks(2).map(|c| {
+        let high = match c.len() {
+            2 => c[1] << 4,
+            _ => 0,
+        };
+
+        (c[0] & 0b00001111) + high
+    }).collect()
+}
+
+pub fn expand(from: &[(u8, u8, u8)]) -> Vec<u8> {
+    let mut new : Vec<u8> = vec![];
+
+    for i in from {
+        new.push(i.0);
+        new.push(i.1);
+        new.push(i.2);
+    }
+
+    new
+}
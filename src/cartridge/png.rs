@@ -0,0 +1,155 @@
+use crate::bitmap::Bitmap;
+
+use super::codec::{zip, unzip};
+use super::error::CartError;
+
+// TIC-80 hides the (zlib compressed) cart in the low bits of the
+// cover image: the first 8 channel bytes carry the payload size 4 bits
+// at a time, then every following channel byte carries as many bits as
+// needed to spread the payload over the rest of the picture.
+// very old exports used a "caRt" png chunk instead, which is still read
+
+pub const PNG_SIGNATURE : [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+pub const COVER_FILL : (u8, u8, u8, u8) = (26, 28, 44, 255);
+
+const HEADER_BYTES : usize = 8;
+const HEADER_BITS : usize = 4;
+const COVER_SIDE : u32 = 256;
+
+pub fn is_png(buf: &[u8]) -> bool {
+    buf.starts_with(&PNG_SIGNATURE)
+}
+
+pub fn is_png_path(path: &str) -> bool {
+    path.to_lowercase().ends_with(".png")
+}
+
+// walk the png chunk list looking for a legacy caRt chunk
+
+fn find_cart_chunk(buf: &[u8]) -> Option<&[u8]> {
+    let mut pos = PNG_SIGNATURE.len();
+
+    while pos + 8 <= buf.len() {
+        let len = u32::from_be_bytes([buf[pos], buf[pos + 1], buf[pos + 2], buf[pos + 3]]) as usize;
+        let name = &buf[pos + 4..pos + 8];
+
+        let start = pos + 8;
+        let end = start.checked_add(len)?;
+
+        if end > buf.len() {
+            return None
+        }
+
+        if name == b"caRt" {
+            return Some(&buf[start..end])
+        }
+
+        // data + crc
+        pos = end + 4;
+    }
+
+    None
+}
+
+fn bit(from: &[u8], at: usize) -> u8 {
+    (from[at / 8] >> (at % 8)) & 1
+}
+
+fn set_bit(to: &mut [u8], at: usize, value: u8) {
+    to[at / 8] = (to[at / 8] & !(1 << (at % 8))) | ((value & 1) << (at % 8));
+}
+
+fn bits_per_byte(size: usize, room: usize) -> usize {
+    (size * 8).div_ceil(room).max(1)
+}
+
+pub fn unhide(pixels: &[u8]) -> Result<Vec<u8>, CartError> {
+    if pixels.len() <= HEADER_BYTES {
+        return Err(CartError::NoCartInPng)
+    }
+
+    let mut size : usize = 0;
+
+    for (i, b) in pixels[..HEADER_BYTES].iter().enumerate() {
+        size |= ((b & 0b00001111) as usize) << (i * HEADER_BITS);
+    }
+
+    let room = pixels.len() - HEADER_BYTES;
+
+    if size == 0 || size > room {
+        return Err(CartError::NoCartInPng)
+    }
+
+    let bits = bits_per_byte(size, room);
+
+    let mut out = vec![0; size];
+
+    for at in 0..size * 8 {
+        let b = pixels[HEADER_BYTES + at / bits];
+
+        set_bit(&mut out, at, b >> (at % bits));
+    }
+
+    Ok(out)
+}
+
+pub fn hide(pixels: &mut [u8], payload: &[u8]) -> Result<(), CartError> {
+    let room = pixels.len().saturating_sub(HEADER_BYTES);
+
+    if payload.len() > room {
+        return Err(CartError::CoverTooSmall { needed : payload.len(), room })
+    }
+
+    for (i, b) in pixels[..HEADER_BYTES].iter_mut().enumerate() {
+        *b = (*b & 0b11110000) | ((payload.len() >> (i * HEADER_BITS)) & 0b00001111) as u8;
+    }
+
+    let bits = bits_per_byte(payload.len(), room);
+
+    for at in 0..payload.len() * 8 {
+        let b = &mut pixels[HEADER_BYTES + at / bits];
+        let shift = at % bits;
+
+        *b = (*b & !(1 << shift)) | (bit(payload, at) << shift);
+    }
+
+    Ok(())
+}
+
+// returns the raw .tic bytes and the picture they were hidden in
+
+pub fn read_png_cart(buf: &[u8]) -> Result<(Vec<u8>, Bitmap), CartError> {
+    let cover = Bitmap::decode(buf)?;
+
+    let payload = match find_cart_chunk(buf) {
+        Some(data) => data.to_vec(),
+        None => unhide(&cover.rgba)?,
+    };
+
+    Ok((unzip(&payload)?, cover))
+}
+
+// a plain cover big enough to hold the payload at 2 bits per channel
+
+pub fn default_cover(payload: usize) -> Bitmap {
+    let mut side = COVER_SIDE;
+
+    while ((side * side * 4) as usize - HEADER_BYTES) * 2 < payload * 8 {
+        side *= 2;
+    }
+
+    Bitmap::new(side, side, COVER_FILL)
+}
+
+pub fn write_png_cart(tic: &[u8], cover: Option<&Bitmap>) -> Result<Vec<u8>, CartError> {
+    let payload = zip(tic);
+
+    let mut img = match cover {
+        Some(c) => c.clone(),
+        None => default_cover(payload.len()),
+    };
+
+    hide(&mut img.rgba, &payload)?;
+
+    img.encode_png()
+}
@@ -0,0 +1,92 @@
+use super::chunk::{Chunk, ChunkType};
+use super::codec::read_size;
+use super::error::CartError;
+
+// type + bank, size low, size high, reserved
+pub const HEADER_SIZE : usize = 4;
+
+// a chunk still pointing into the file it was read from
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkRef<'a> {
+    pub offset : usize,
+    pub kind : ChunkType,
+    pub bank : u8,
+    pub data : &'a [u8],
+}
+
+impl ChunkRef<'_> {
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(self.kind, self.bank, self.data.to_vec())
+    }
+}
+
+// walks the chunks of a .tic without copying anything,
+// stopping at the first malformed one
+
+pub struct ChunkReader<'a> {
+    buf : &'a [u8],
+    pos : usize,
+    failed : bool,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(buf: &'a [u8]) -> ChunkReader<'a> {
+        ChunkReader {
+            buf,
+            pos : 0,
+            failed : false,
+        }
+    }
+
+    fn next_chunk(&mut self) -> Result<ChunkRef<'a>, CartError> {
+        let offset = self.pos;
+        let left = self.buf.len() - offset;
+
+        if left < HEADER_SIZE {
+            return Err(CartError::TruncatedHeader { offset, left })
+        }
+
+        let header = &self.buf[offset..offset + HEADER_SIZE];
+
+        let kind = ChunkType::from_id(header[0]);
+        let bank = (header[0] & 0b11100000) >> 5;
+
+        if let ChunkType::Reserved(id) = kind {
+            return Err(CartError::UnknownId { offset, id })
+        }
+
+        let size = read_size(kind, header[1] as usize + ((header[2] as usize) << 8));
+
+        if size > left - HEADER_SIZE {
+            return Err(CartError::Overrun { offset, kind, size, left : left - HEADER_SIZE })
+        }
+
+        let start = offset + HEADER_SIZE;
+
+        self.pos = start + size;
+
+        Ok(ChunkRef {
+            offset,
+            kind,
+            bank,
+            data : &self.buf[start..start + size],
+        })
+    }
+}
+
+impl<'a> Iterator for ChunkReader<'a> {
+    type Item = Result<ChunkRef<'a>, CartError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos >= self.buf.len() {
+            return None
+        }
+
+        let chunk = self.next_chunk();
+
+        self.failed = chunk.is_err();
+
+        Some(chunk)
+    }
+}
@@ -0,0 +1,305 @@
+// text editing and lua highlighting for the code editor
+
+pub const TAB_WIDTH : usize = 2;
+
+const KEYWORDS : [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if",
+    "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Ident,
+    Number,
+    Str,
+    Comment,
+    Symbol,
+    Space,
+}
+
+// a run of chars [start, end) on one line
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind : TokenKind,
+    pub start : usize,
+    pub end : usize,
+}
+
+// splits one line into tokens; `in_comment` says whether the line starts
+// inside a --[[ ]] block, the returned bool whether the next one does
+
+pub fn highlight_line(line: &str, in_comment: bool) -> (Vec<Token>, bool) {
+    let chars : Vec<char> = line.chars().collect();
+    let mut tokens : Vec<Token> = vec![];
+    let mut i = 0;
+
+    let close_comment = |from: usize| -> Option<usize> {
+        (from..chars.len().saturating_sub(1)).find(|&k| chars[k] == ']' && chars[k + 1] == ']').map(|k| k + 2)
+    };
+
+    if in_comment {
+        match close_comment(0) {
+            Some(end) => {
+                tokens.push(Token { kind : TokenKind::Comment, start : 0, end });
+                i = end;
+            },
+            None => {
+                tokens.push(Token { kind : TokenKind::Comment, start : 0, end : chars.len() });
+                return (tokens, true)
+            }
+        }
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        let kind = if c == '-' && chars.get(i + 1) == Some(&'-') {
+            if chars.get(i + 2) == Some(&'[') && chars.get(i + 3) == Some(&'[') {
+                match close_comment(i + 4) {
+                    Some(end) => i = end,
+                    None => {
+                        tokens.push(Token { kind : TokenKind::Comment, start, end : chars.len() });
+                        return (tokens, true)
+                    }
+                }
+            } else {
+                i = chars.len();
+            }
+
+            TokenKind::Comment
+        } else if c == '"' || c == '\'' {
+            i += 1;
+
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+
+            i = (i + 1).min(chars.len());
+
+            TokenKind::Str
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+
+            TokenKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let word : String = chars[start..i].iter().collect();
+
+            match KEYWORDS.contains(&word.as_str()) {
+                true => TokenKind::Keyword,
+                false => TokenKind::Ident,
+            }
+        } else if c.is_whitespace() {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+
+            TokenKind::Space
+        } else {
+            i += 1;
+
+            TokenKind::Symbol
+        };
+
+        tokens.push(Token { kind, start, end : i });
+    }
+
+    (tokens, false)
+}
+
+// the program as lines of text with a cursor, positions are in chars;
+// crlf remembers whether the lines were split on \r\n so they are joined back the same way
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextBuffer {
+    pub lines : Vec<String>,
+    pub line : usize,
+    pub col : usize,
+    pub crlf : bool,
+}
+
+fn byte_at(s: &str, col: usize) -> usize {
+    s.char_indices().nth(col).map_or(s.len(), |(b, _)| b)
+}
+
+impl TextBuffer {
+    pub fn new(text: &str) -> TextBuffer {
+        let mut lines : Vec<String> = text.split('\n').map(|l| l.trim_end_matches('\r').to_string()).collect();
+
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        TextBuffer { lines, line : 0, col : 0, crlf : text.contains("\r\n") }
+    }
+
+    pub fn text(&self) -> String {
+        match self.crlf {
+            true => self.lines.join("\r\n"),
+            false => self.lines.join("\n"),
+        }
+    }
+
+    fn line_len(&self, line: usize) -> usize {
+        self.lines[line].chars().count()
+    }
+
+    pub fn set_cursor(&mut self, line: usize, col: usize) {
+        self.line = line.min(self.lines.len() - 1);
+        self.col = col.min(self.line_len(self.line));
+    }
+
+    pub fn insert(&mut self, c: char) {
+        if c == '\n' {
+            self.newline();
+            return
+        }
+
+        let at = byte_at(&self.lines[self.line], self.col);
+
+        self.lines[self.line].insert(at, c);
+        self.col += 1;
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.insert(c);
+        }
+    }
+
+    pub fn newline(&mut self) {
+        let at = byte_at(&self.lines[self.line], self.col);
+        let rest = self.lines[self.line].split_off(at);
+
+        self.lines.insert(self.line + 1, rest);
+        self.line += 1;
+        self.col = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+
+            let at = byte_at(&self.lines[self.line], self.col);
+            self.lines[self.line].remove(at);
+        } else if self.line > 0 {
+            let rest = self.lines.remove(self.line);
+
+            self.line -= 1;
+            self.col = self.line_len(self.line);
+            self.lines[self.line].push_str(&rest);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.line) {
+            let at = byte_at(&self.lines[self.line], self.col);
+            self.lines[self.line].remove(at);
+        } else if self.line + 1 < self.lines.len() {
+            let next = self.lines.remove(self.line + 1);
+            self.lines[self.line].push_str(&next);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.line > 0 {
+            self.line -= 1;
+            self.col = self.line_len(self.line);
+        }
+    }
+
+    pub fn right(&mut self) {
+        if self.col < self.line_len(self.line) {
+            self.col += 1;
+        } else if self.line + 1 < self.lines.len() {
+            self.line += 1;
+            self.col = 0;
+        }
+    }
+
+    pub fn up(&mut self, by: usize) {
+        self.set_cursor(self.line.saturating_sub(by), self.col);
+    }
+
+    pub fn down(&mut self, by: usize) {
+        self.set_cursor(self.line + by, self.col);
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.line_len(self.line);
+    }
+
+    // next match after the cursor, wrapping around to the top
+
+    pub fn find_next(&self, query: &str) -> Option<(usize, usize)> {
+        if query.is_empty() {
+            return None
+        }
+
+        let count = self.lines.len();
+
+        for step in 0..=count {
+            let line = (self.line + step) % count;
+            let text = &self.lines[line];
+
+            let from = match step {
+                0 => byte_at(text, self.col + 1),
+                _ => 0,
+            };
+
+            if let Some(b) = text[from..].find(query) {
+                return Some((line, text[..from + b].chars().count()))
+            }
+        }
+
+        None
+    }
+
+    // moves the cursor to the next match, returns whether there was one
+
+    pub fn find(&mut self, query: &str) -> bool {
+        match self.find_next(query) {
+            Some((line, col)) => {
+                self.set_cursor(line, col);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // matches never span lines, returns how many were replaced
+
+    pub fn replace_all(&mut self, query: &str, with: &str) -> usize {
+        if query.is_empty() || query.contains('\n') {
+            return 0
+        }
+
+        let mut count = 0;
+
+        for l in self.lines.iter_mut() {
+            count += l.matches(query).count();
+            *l = l.replace(query, with);
+        }
+
+        self.set_cursor(self.line, self.col);
+
+        count
+    }
+}
@@ -0,0 +1,65 @@
+// one byte of 8 flags for each of the 256 tiles followed by the 256 sprites
+
+pub const FLAG_COUNT : usize = 512;
+pub const SPRITE_FLAGS : usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Flags {
+    pub bits : Vec<u8>,
+}
+
+impl Default for Flags {
+    fn default() -> Flags {
+        Flags { bits : vec![0; FLAG_COUNT] }
+    }
+}
+
+impl Flags {
+    pub fn from_chunk(data: &[u8]) -> Flags {
+        let mut flags = Flags::default();
+
+        let len = data.len().min(FLAG_COUNT);
+        flags.bits[..len].copy_from_slice(&data[..len]);
+
+        flags
+    }
+
+    // trailing zeros are dropped, like TIC-80 does
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let used = self.bits.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        self.bits[..used].to_vec()
+    }
+
+    pub fn get(&self, id: usize, flag: u8) -> bool {
+        self.bits[id] & (1 << flag) != 0
+    }
+
+    pub fn set(&mut self, id: usize, flag: u8, on: bool) {
+        match on {
+            true => self.bits[id] |= 1 << flag,
+            false => self.bits[id] &= !(1 << flag),
+        }
+    }
+
+    pub fn toggle(&mut self, id: usize, flag: u8) {
+        self.bits[id] ^= 1 << flag;
+    }
+
+    // how many of the given ids have the flag set
+
+    pub fn count(&self, ids: &[usize], flag: u8) -> usize {
+        ids.iter().filter(|&&i| self.get(i, flag)).count()
+    }
+
+    // sets the flag on all ids, unless they all have it already, then clears it
+
+    pub fn toggle_all(&mut self, ids: &[usize], flag: u8) {
+        let on = self.count(ids, flag) < ids.len();
+
+        for &i in ids {
+            self.set(i, flag, on);
+        }
+    }
+}
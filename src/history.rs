@@ -0,0 +1,116 @@
+// undo / redo for the sheet and palette editors, each edit keeps the state
+// it replaced and the one it left behind so it can be stepped both ways
+
+pub const HISTORY_LIMIT : usize = 256;
+
+// one page of a sheet read below 4bpp
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SheetPage {
+    pub sprites : bool,
+    pub bank : usize,
+    pub bpp : u8,
+    pub page : usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    // the blocks being drawn on, before they are written back to the sheet
+    Selection { before : Vec<Vec<u8>>, after : Vec<Vec<u8>> },
+
+    // a whole sheet, or one page of it when viewed below 4bpp
+    Sheet { sprites : bool, bank : usize, bpp : u8, page : usize, before : Vec<Vec<u8>>, after : Vec<Vec<u8>> },
+
+    Palette { ovr : bool, before : Vec<(u8, u8, u8)>, after : Vec<(u8, u8, u8)> },
+
+    // two colours traded places along with every pixel using them (in
+    // every sheet, or only `page` below 4bpp), doing it again undoes it
+    Swap { ovr : bool, a : usize, b : usize, page : Option<SheetPage> },
+}
+
+impl Edit {
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Edit::Selection { before, after } => before == after,
+            Edit::Sheet { before, after, .. } => before == after,
+            Edit::Palette { before, after, .. } => before == after,
+            Edit::Swap { a, b, .. } => a == b,
+        }
+    }
+
+    // the same edit going the other way
+    pub fn reversed(&self) -> Edit {
+        let mut rev = self.clone();
+
+        match &mut rev {
+            Edit::Selection { before, after } => std::mem::swap(before, after),
+            Edit::Sheet { before, after, .. } => std::mem::swap(before, after),
+            Edit::Palette { before, after, .. } => std::mem::swap(before, after),
+            Edit::Swap { .. } => {},
+        }
+
+        rev
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    pub done : Vec<Edit>,
+    pub undone : Vec<Edit>,
+}
+
+impl History {
+    // a new edit forgets whatever was undone, the oldest edits fall off
+    // past HISTORY_LIMIT
+
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_noop() {
+            return
+        }
+
+        self.done.push(edit);
+        self.undone.clear();
+
+        if self.done.len() > HISTORY_LIMIT {
+            self.done.remove(0);
+        }
+    }
+
+    // the returned edit is the one to apply: its `after` is the state to go
+    // back to; nothing happens if `allowed` turns down the last edit
+
+    pub fn undo(&mut self, allowed: impl Fn(&Edit) -> bool) -> Option<Edit> {
+        if !allowed(self.done.last()?) {
+            return None
+        }
+
+        let edit = self.done.pop()?;
+        let rev = edit.reversed();
+
+        self.undone.push(edit);
+
+        Some(rev)
+    }
+
+    pub fn redo(&mut self, allowed: impl Fn(&Edit) -> bool) -> Option<Edit> {
+        if !allowed(self.undone.last()?) {
+            return None
+        }
+
+        let edit = self.undone.pop()?;
+
+        self.done.push(edit.clone());
+
+        Some(edit)
+    }
+
+    // once a selection is written back its strokes are replaced by the one
+    // sheet edit they add up to
+
+    pub fn squash(&mut self, edit: Edit) {
+        self.done.retain(|e| !matches!(e, Edit::Selection { .. }));
+        self.undone.retain(|e| !matches!(e, Edit::Selection { .. }));
+
+        self.push(edit);
+    }
+}
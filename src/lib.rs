@@ -0,0 +1,15 @@
+pub mod bitmap;
+pub mod cartridge;
+pub mod code;
+pub mod flags;
+pub mod history;
+pub mod map;
+pub mod music;
+pub mod palette;
+pub mod quantise;
+pub mod screen;
+pub mod sfx;
+pub mod sheet;
+pub mod synth;
+pub mod wav;
+pub mod wave;
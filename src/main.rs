@@ -1,10 +1,9 @@
 
-use std::{env, process::{Command, exit}, fs, fs::File, io::{Read, Write}};
+use std::{env, fs, path::Path, process::{Command, exit}};
 use macroquad::prelude::*;
+use artic_editor2::{bitmap::Bitmap, cartridge::{CartError, ChunkType, Cartridge, MAX_BANKS, write_atomic}, code::{TAB_WIDTH, TextBuffer, TokenKind, highlight_line}, flags::{Flags, SPRITE_FLAGS}, history::{Edit, History, SheetPage}, map::{MAP_HEIGHT, MAP_WIDTH, SCREEN_TILES_H, SCREEN_TILES_W, Map, Stamp}, music::{CHANNELS, COMMANDS, FRAME_COUNT, NOTE_FIRST, NOTE_NONE, NOTE_STOP, PATTERN_COUNT, PATTERN_ROWS, Music, Patterns, Row}, palette::{PALETTE_FORMATS, PaletteFormat, fit_palette, hsv_to_rgb, parse_hex, permute_palette, read_palette, remap_pixels, rgb_to_hsv, swap_permutation, to_hex, write_palette}, quantise::quantise, screen::{SCREEN_H, SCREEN_W, Screen}, sfx::{ENVELOPES, Loop, NOTE_NAMES, SFX_COUNT, SFX_TICKS, Sounds}, sheet::{BPP_MODES, bpp_page, bpp_pages, flood_fill, join, place, remap_bpp_page, render, render_sheet, set_bpp_page, shift, slice}, synth::{render_sfx, render_track}, wav::{SAMPLE_RATE, encode_wav}, wave::{Preset, WAVE_COUNT, WAVE_MAX, WAVE_SAMPLES, Waves, preset, preview}};
 
 // define constants
-const IMPORTANT : [&str; 3] = ["Palette", "Tiles", "Sprites"];
-const DEFAULT_PALETTE : [u8; 48] = [26, 28, 44, 93, 39, 93, 177, 62, 83, 239, 125, 87, 255, 205, 117, 167, 240, 112, 56, 183, 100, 37, 113, 121, 41, 54, 111, 59, 93, 201, 65, 166, 246, 115, 239, 247, 244, 244, 244, 148, 176, 194, 86, 108, 134, 51, 60, 87];
 const PIX_SIZE : f32 = 4.0;
 const SPR_SIDE_LENGTH : f32 = 8.0 * PIX_SIZE;
 const ALL_SIDE_LENGTH : f32 = SPR_SIDE_LENGTH * 16.0;
@@ -16,183 +15,44 @@ const PALETTE_SIZE : f32 = 6.0 * PIX_SIZE;
 const SELECTION_THICK : f32 = 8.0;
 const EMPTY_SPR : [u8; 64] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 const IMG_EXTENTIONS : [&str; 3] = ["jpg", "jpeg", "png"];
-
-#[derive(Clone, Debug)]
-struct Chunk {
-    bank : u8,
-    data : Vec<u8>,
-    name : String,
-}
-
-fn build_chunk(c_bank: u8, c_data: &Vec<u8>, c_name: String) -> Chunk {
-
-    // .clone() just to be sure
-
-    Chunk{
-        bank : c_bank.clone(),
-        data : c_data.clone(),
-        name : c_name.clone(),
-    }
-}
-
-fn deconstruct_tic(path: String) -> Vec<Chunk> {
-    // reading the .tic file
-    let mut f = File::open(String::from(path.clone())).expect("No file found!");
-
-    // get file size
-    let size : u64 = fs::metadata(path.clone()).expect("No file found").len();
-
-    // creating a vector to store the bytes
-    let mut buf = vec![0; size as usize];
-
-    // put the bytes into the vector
-    let _ = f.read_exact(&mut buf);
-
-    // TODO :
-    // separate chunks
-
-    let mut chunks : Vec<Chunk> = vec![];
-    let mut check = 0;
-
-    // static types are good
-
-    let mut chunk_size : u16 = 0;
-    let mut chunk_bank : u8 = 0;
-    let mut chunk_type : &str = "";
-    let mut chunk_data : Vec<u8> = vec![];
-
-    for i in buf {
-
-        // chunks follow the scheme of
-        // type(5 bits) + bank(3 bits)
-
-        chunk_type = match check {
-            0 => match i & 0b00011111 {
-                1 => "Tiles",
-                2 => "Sprites",
-                4 => "Map",
-                5 => "Code",
-                6 => "Flags",
-                9 => "Samples",
-                10 => "Waveform",
-                12 => "Palette",
-                14 => "Music",
-                15 => "Patterns",
-                17 => "Default",
-                18 => "Screen",
-                19 => "Binary",
-                _ => "(Reserved)"
-                },
-            _ => chunk_type,
-        };
-        chunk_bank = match check {
-            0 => i & 0b11100000,
-            _ => chunk_bank,
-        };
-
-        // size(16 bits)
-
-        chunk_size = match check {
-            1 => i as u16,
-            2 => chunk_size + ((i as u16) << 8),
-            _ => chunk_size,
-        };
-
-        // reserved(8 bits)
-
-        // actual data(size bits)
-
-        if check == 4 {
-            if chunk_size > 0 {
-                chunk_size -= 1;
-                chunk_data.push(i);
-            } else {
-                check = 0;
-            }
-        }
-
-        // handle data insertion
-
-        if check < 3 {
-            // cycle state
-
-            check += 1;
-        } else if chunk_size == 0 {
-            // reset state
-
-            check = 0;
-
-            // add chunk
-
-            chunks.push(
-                build_chunk(
-                    chunk_bank,
-                    &chunk_data,
-                    chunk_type.into()
-                )
-            );
-            chunk_data.clear();
-        } else {
-            // set state
-
-            check = 4;
-        }
-    }
-
-    chunks
-}
-
-fn extract(from: Vec<Chunk>, name: String) -> Chunk {
-    for i in from {
-        if i.name == name {
-            return i
-        }
-    }
-
-    return Chunk{
-        bank : 0,
-        data : vec![],
-        name : name,
-    }
-}
-
-fn replace(from: Vec<Chunk>, what: Chunk) -> Vec<Chunk> {
-    let mut new : Vec<Chunk> = vec![];
-
-    let mut added : bool = false;
-
-    for i in from {
-        if i.name == what.name {
-            new.push(what.clone());
-            added = true;
-        } else {
-            new.push(i);
-        }
-    }
-
-    if !added {
-        new.push(what.clone());
-    }
-
-    new
-}
-
-fn find(from: Vec<Chunk>, name: String) -> bool {
-    for i in from {
-        if i.name == name {
-            return true
-        }
-    }
-
-    false
-}
+const PAL_EXTENTIONS : [&str; 4] = ["pal", "gpl", "hex", "png"];
+const CART_EXTENTIONS : [&str; 2] = ["tic", "png"];
+const KEEP_BACKUP : bool = true;
+const MAP_TOP : f32 = 30.0;
+const CODE_TOP : f32 = 50.0;
+const CODE_LINE : f32 = 18.0;
+const CODE_GUTTER : f32 = 50.0;
+const WAVE_X : f32 = 150.0;
+const WAVE_Y : f32 = 60.0;
+const WAVE_BAR_W : f32 = 18.0;
+const WAVE_BAR_H : f32 = 28.0;
+const SFX_X : f32 = 120.0;
+const SFX_TOP : f32 = 60.0;
+const SFX_CELL_W : f32 = 20.0;
+const SFX_LEVEL_H : f32 = 5.0;
+const SFX_LANE : f32 = 120.0;
+const TRACK_TOP : f32 = 70.0;
+const TRACK_ROW : f32 = 16.0;
+const TRACK_X : f32 = 200.0;
+const TRACK_CHANNEL_W : f32 = 145.0;
+const TRACK_VISIBLE : usize = 30;
+const COVER_SCALE : f32 = 3.0;
+const COVER_TOP : f32 = 70.0;
+const SLIDER_X : f32 = 40.0;
+const SLIDER_W : f32 = 180.0;
+const SLIDER_TOP : f32 = 250.0;
+const SWATCH : f32 = 24.0;
+
+// note, octave, sfx, command, param 1, param 2
+const ROW_FIELDS : usize = 6;
+const FLAG_COLOURS : [Color; 8] = [RED, ORANGE, YELLOW, GREEN, SKYBLUE, BLUE, PURPLE, PINK];
 
 fn get_files(path: String) -> Vec<String> {
 
     let mut list_files = Command::new("ls");
 
     list_files.arg("-la");
-    if path.len() > 0 {
+    if !path.is_empty() {
         list_files.arg(path.clone());
     }
 
@@ -217,54 +77,10 @@ fn get_files(path: String) -> Vec<String> {
 }
 
 fn explore_path(from: String, into: String) -> (String, Vec<String>) {
-    (from.clone() + into.as_str() + "/", get_files((from + into.as_str() + "/").into()))
-}
-
-fn flatten(thick : Vec<Vec<u8>>) -> Vec<u8> {
-    let mut new : Vec<u8> = vec![];
-
-    for i in thick {
-        for k in i {
-            new.push(k);
-        }
-    }
-
-    new
-}
-
-fn compress(wide : Vec<u8>) -> Vec<u8> {
-    let mut now : u8 = 0;
-    let mut chn : i32 = 0;
-
-    let mut new : Vec<u8> = vec![];
-
-    for i in wide {
-        if chn == 0 {
-            now = i.into();
-        } else {
-            now += i << 4;
-            new.push(now);
-            now = 0;
-        }
-        chn = 1 - chn;
-    }
-
-    new
-}
-
-fn expand(from: Vec<(u8, u8, u8)>) -> Vec<u8> {
-    let mut new : Vec<u8> = vec![];
-
-    for i in from {
-        new.push(i.0);
-        new.push(i.1);
-        new.push(i.2);
-    }
-
-    new
+    (from.clone() + into.as_str() + "/", get_files(from + into.as_str() + "/"))
 }
 
-fn draw_img(what: Vec<i32>) -> () {
+fn draw_img(what: Vec<i32>) {
     let mut idx = 0;
 
     let col = [BLACK, WHITE];
@@ -277,62 +93,83 @@ fn draw_img(what: Vec<i32>) -> () {
             idx += 1;
         }
         cid += 1;
-        cid = cid % 2;
+        cid %= 2;
     }
 }
 
-fn construct_tic(path: String, from: Vec<Chunk>) -> () {
-    println!("{}", path);
+fn draw_sheet(draw: &[Vec<u8>], palette: &[(u8, u8, u8)], black_pal: (u8, u8, u8)) {
+    draw_rectangle(OFF_X , OFF_Y , ALL_SIDE_LENGTH , ALL_SIDE_LENGTH, color_u8!(255, 255, 255, 125));
+    draw_rectangle(OFF_X , OFF_Y , ALL_SIDE_LENGTH, ALL_SIDE_LENGTH, color_u8!(black_pal.0, black_pal.1, black_pal.2, 125));
 
-    let mut file = fs::OpenOptions::new().create(true).write(true).open(path).expect("No");
+    for (id, i) in draw.iter().enumerate() {
+        for y in 0..8 {
+            for k in 0..8 {
+                let col = palette[i[k + y*8] as usize];
+                let my_col = color_u8!(col.0, col.1, col.2, 255);
 
-    for i in &from {
-        let type_id = match i.name.as_str() {
-            "Tiles" => 1,
-            "Sprites" => 2,
-            "Map" => 4,
-            "Code" => 5,
-            "Flags" => 6,
-            "Samples" => 9,
-            "Waveform" => 10,
-            "Palette" => 12,
-            "Music" => 14,
-            "Patterns" => 15,
-            "Default" => 17,
-            "Screen" => 18,
-            "Binary" => 19,
-            _ => 32,
-        } & 0b00011111 ;
+                let sx = ((id as f32)%16.0)*PIX_SIZE*8.0;
+                let sy = ((id as f32)/16.0).floor()*PIX_SIZE*8.0;
 
-        println!("{}", type_id);
+                let px = OFF_X + (k as f32)*PIX_SIZE + sx;
+                let py = OFF_Y + (y as f32)*PIX_SIZE + sy;
 
-        let size = i.data.len() as u16;
+                draw_rectangle(px, py, PIX_SIZE, PIX_SIZE, my_col);
+            }
+        }
+    }
+}
 
-        println!("{}", size);
+// <cart name>_<what>.png next to the cart
 
-        let size_low : u8 = (size & 0b0000000011111111) as u8;
-        let size_high : u8 = ((size & 0b1111111100000000) >> 8) as u8;
+fn export_path(file_path: &str, what: &str, ext: &str) -> String {
+    let stem = Path::new(file_path).with_extension("");
 
-        println!("{} {}", size_low, size_high);
+    format!("{}_{}.{}", stem.to_string_lossy(), what, ext)
+}
 
-        let bank = i.bank >> 5;
+// returns the (error, info) messages to show
 
-        println!("{}", bank);
+fn export_png(img: &Bitmap, path: &str) -> (String, String) {
+    match img.encode_png().and_then(|b| write_atomic(Path::new(path), &b, false)) {
+        Ok(()) => (String::new(), format!("exported {}", path)),
+        Err(e) => (format!("could not export {} : {}", path, e), String::new()),
+    }
+}
 
-        let mut chunk_bytes : Vec<u8> = vec![];
+fn export_wav(samples: &[i16], path: &str) -> (String, String) {
+    match write_atomic(Path::new(path), &encode_wav(samples, SAMPLE_RATE), false) {
+        Ok(()) => (String::new(), format!("exported {}", path)),
+        Err(e) => (format!("could not export {} : {}", path, e), String::new()),
+    }
+}
 
-        println!("{}", bank + type_id);
+// the selection being edited (to_draw) goes column by column, these turn
+// it into the usual row by row order and back
 
-        chunk_bytes.push(bank + type_id);
-        chunk_bytes.push(size_low);
-        chunk_bytes.push(size_high);
-        chunk_bytes.push(0);
+fn to_rows(columns: &[Vec<u8>], rows: usize) -> Vec<Vec<u8>> {
+    let rows = rows.max(1);
+    let cols = columns.len() / rows;
 
-        for k in i.data.clone() {
-            chunk_bytes.push(k);
-        }
+    (0..columns.len()).map(|i| columns[i % cols * rows + i / cols].clone()).collect()
+}
+
+fn to_columns(blocks: &[Vec<u8>], rows: usize) -> Vec<Vec<u8>> {
+    let rows = rows.max(1);
+    let cols = blocks.len() / rows;
+
+    (0..blocks.len()).map(|i| blocks[i % rows * cols + i / rows].clone()).collect()
+}
 
-        let _ = file.write_all(&chunk_bytes);
+// the sheet cell under the mouse, if any
+
+fn sheet_cell(mx: f32, my: f32) -> Option<usize> {
+    let cx = ((mx - OFF_X) / SPR_SIDE_LENGTH).floor();
+    let cy = ((my - OFF_Y) / SPR_SIDE_LENGTH).floor();
+
+    if (0.0..16.0).contains(&cx) && (0.0..16.0).contains(&cy) {
+        Some(cx as usize + cy as usize * 16)
+    } else {
+        None
     }
 }
 
@@ -363,7 +200,7 @@ async fn main() {
     if args.len() > 1 {
         let par : Vec<&str> = args[1].split(".").collect();
 
-        if par[par.len()-1] == "tic" {
+        if CART_EXTENTIONS.contains(&par[par.len()-1]) {
             current_state = "read_file";
             file_path = args[1].clone();
         } else {
@@ -377,7 +214,27 @@ async fn main() {
     let mut tiles : Vec<Vec<u8>> = vec![];
     let mut sprites : Vec<Vec<u8>> = vec![];
 
-    let mut gottem = get_files(search_path.clone().into());
+    // tiles and sprites hold the bank being shown, the rest wait in here
+
+    let mut tile_banks : Vec<Vec<Vec<u8>>> = vec![vec![]; MAX_BANKS as usize];
+    let mut sprite_banks : Vec<Vec<Vec<u8>>> = vec![vec![]; MAX_BANKS as usize];
+
+    // the banks as they were read, saving only writes the ones that differ
+
+    let mut loaded_tiles : Vec<Vec<Vec<u8>>> = vec![vec![]; MAX_BANKS as usize];
+    let mut loaded_sprites : Vec<Vec<Vec<u8>>> = vec![vec![]; MAX_BANKS as usize];
+    let mut sheet_bank : usize = 0;
+
+    // below 4bpp the sheet shows a page of the bank's bytes read with fewer
+    // bits, edited through bpp_view and written back whenever it changes
+
+    let mut sheet_bpp : u8 = 4;
+    let mut sheet_page : usize = 0;
+    let mut bpp_view : Vec<Vec<u8>> = vec![];
+    let mut bpp_synced : Vec<Vec<u8>> = vec![];
+    let mut bpp_key : Option<(u8, usize, bool, usize)> = None;
+
+    let mut gottem = get_files(search_path.clone());
 
     gottem.remove(0);
     gottem.remove(0);
@@ -388,8 +245,113 @@ async fn main() {
     let mut secondary = 0;
 
     let mut to_draw : Vec<Vec<u8>> = vec![];
+    let mut fill_mode : bool = false;
+    let mut sheet_clip : Option<(Vec<Vec<u8>>, usize)> = None;
 
-    let mut chunks : Vec<Chunk> = vec![];
+    // undo history, strokes and palette tweaks are recorded once the mouse
+    // lets go, by comparing against the marks
+
+    let mut history = History::default();
+    let mut sel_mark : Vec<Vec<u8>> = vec![];
+    let mut pal_mark : Vec<(u8, u8, u8)> = vec![];
+    let mut ovr_mark : Vec<(u8, u8, u8)> = vec![];
+
+    let mut cart = Cartridge::new();
+
+    let mut error_msg = String::new();
+    let mut info_msg = String::new();
+
+    // map editor
+
+    // map and flags follow the sheet bank, the other banks wait in map_banks
+    // and flag_banks like the sheets do
+
+    let mut map = Map::default();
+    let mut map_banks : Vec<Map> = vec![Map::default(); MAX_BANKS as usize];
+    let mut map_edited : Vec<bool> = vec![false; MAX_BANKS as usize];
+    let (mut map_x, mut map_y) : (usize, usize) = (0, 0);
+    let mut map_zoom : f32 = 2.0;
+    let mut map_tex : Option<Texture2D> = None;
+    let mut stamp = Stamp::single(0);
+    let mut picking : bool = false;
+    let mut pick_from : usize = 0;
+
+    // sprite flags
+
+    let mut flags = Flags::default();
+    let mut flag_banks : Vec<Flags> = vec![Flags::default(); MAX_BANKS as usize];
+    let mut flags_edited : Vec<bool> = vec![false; MAX_BANKS as usize];
+    let mut flag_mode : bool = false;
+    let mut flag_overlay : Option<u8> = None;
+
+    // code editor
+
+    let mut code_buf = TextBuffer::new("");
+    let mut code_edited : bool = false;
+    let mut code_error = String::new();
+    let mut code_scroll : usize = 0;
+    let mut code_prompt : &str = "";
+    let mut code_find = String::new();
+    let mut code_with = String::new();
+
+    // waveform editor
+
+    let mut waves = Waves::default();
+    let mut waves_edited : bool = false;
+    let mut wave_sel : usize = 0;
+    let mut wave_clip : Option<Vec<u8>> = None;
+
+    // sfx editor
+
+    let mut sounds = Sounds::default();
+    let mut sounds_edited : bool = false;
+    let mut sfx_sel : usize = 0;
+    let mut sfx_loop_drag : Option<(usize, u8)> = None;
+
+    // music tracker
+
+    let mut patterns = Patterns::default();
+    let mut patterns_edited : bool = false;
+    let mut music = Music::default();
+    let mut music_edited : bool = false;
+    let mut track_sel : usize = 0;
+    let mut frame_sel : usize = 0;
+    let (mut row_sel, mut field_sel) : (usize, usize) = (0, 0);
+    let mut row_scroll : usize = 0;
+
+    // png export
+
+    let mut export_alpha : bool = false;
+    let mut export_scale : u32 = 1;
+
+    // palette editor
+
+    let mut palette_edited : bool = false;
+    let mut ovr_palette : Vec<(u8, u8, u8)> = vec![];
+    let mut ovr_edited : bool = false;
+    let mut pal_ovr : bool = false;
+    let mut pal_sel : usize = 0;
+    let mut pal_hsv : (f32, f32, f32) = (0.0, 0.0, 0.0);
+    let mut pal_hex = String::new();
+    let mut pal_drag : Option<usize> = None;
+    let mut pal_back : &str = "main";
+    let mut pal_format : usize = 0;
+    let mut pal_swap : Option<(bool, usize, usize)> = None;
+
+    // screen / cover
+
+    let mut screen = Screen::default();
+    let mut screen_edited : bool = false;
+    let mut screen_tex : Option<Texture2D> = None;
+
+    // image import, into the sheet or the screen
+
+    let mut importing : bool = false;
+    let mut import_into : &str = "sheet";
+    let mut import_src : Option<Bitmap> = None;
+    let mut import_pix : Vec<u8> = vec![];
+    let mut import_dither : bool = false;
+    let mut import_at : usize = 0;
 
     loop {
         let current_press_l = is_mouse_button_down(MouseButton::Left);
@@ -399,14 +361,51 @@ async fn main() {
 
         let (mx, my) : (f32, f32) = mouse_position();
 
-        let draw = match show_spr {
-            true => &mut sprites,
-            false => &mut tiles,
+        if sheet_bpp == 4 {
+            bpp_key = None;
+        } else {
+            let sheet = match show_spr {
+                true => &mut sprites,
+                false => &mut tiles,
+            };
+
+            let key = (sheet_bpp, sheet_page, show_spr, sheet_bank);
+
+            if bpp_key == Some(key) && bpp_view != bpp_synced {
+                set_bpp_page(sheet, &bpp_view, sheet_bpp, sheet_page);
+                bpp_synced = bpp_view.clone();
+                map_tex = None;
+            }
+
+            if bpp_key != Some(key) {
+                bpp_view = bpp_page(sheet, sheet_bpp, sheet_page);
+                bpp_synced = bpp_view.clone();
+                bpp_key = Some(key);
+            }
+        }
+
+        let draw = match (sheet_bpp, show_spr) {
+            (4, true) => &mut sprites,
+            (4, false) => &mut tiles,
+            _ => &mut bpp_view,
         };
 
         match current_state {
             "open" => {
-                draw_text("Select a file", 50.0, 50.0, 25.0, WHITE);
+                match importing {
+                    true => draw_text("Select an image to import (Esc to go back)", 50.0, 50.0, 25.0, WHITE),
+                    false => draw_text("Select a file", 50.0, 50.0, 25.0, WHITE),
+                };
+
+                if importing && is_key_pressed(KeyCode::Escape) {
+                    importing = false;
+
+                    current_state = match import_into {
+                        "screen" => "screen",
+                        "palette" => "palette",
+                        _ => "main",
+                    };
+                }
 
                 let max = match gottem.len() > 19 {
                     true => 19,
@@ -441,9 +440,15 @@ async fn main() {
                     let par : Vec<&str> = text.split(".").collect();
 
                     let is_tic = par[par.len()-1] == "tic";
-                    let is_img = IMG_EXTENTIONS.contains(&par[par.len()-1]);
+                    // palettes can come from a few text formats too
+
+                    let is_img = match import_into {
+                        "palette" => PAL_EXTENTIONS.contains(&par[par.len()-1]),
+                        _ => IMG_EXTENTIONS.contains(&par[par.len()-1]),
+                    };
+                    let is_cart = CART_EXTENTIONS.contains(&par[par.len()-1]);
 
-                    let txt_size = measure_text(&name, None, 25, 1.0);
+                    let txt_size = measure_text(name, None, 25, 1.0);
 
                     let is_sel : bool = my >= ypos - txt_size.height && my < ypos;
 
@@ -469,7 +474,7 @@ async fn main() {
                             }
 
                             for _i in 0..2 {
-                                if gottem.len() > 0 {
+                                if !gottem.is_empty() {
                                     gottem.remove(0);
                                 }
                             }
@@ -477,8 +482,84 @@ async fn main() {
                             offset = 0.0;
 
                             break
-                        } else if is_tic {
-                            if search_path.len() > 0 {
+                        } else if importing && is_img {
+                            let img_path = match search_path.is_empty() {
+                                true => name.to_string(),
+                                false => search_path.clone() + "/" + name,
+                            };
+
+                            if import_into == "palette" {
+                                let format = PaletteFormat::from_path(&img_path).unwrap_or(PaletteFormat::Png);
+
+                                match fs::read(&img_path).map_err(CartError::from).and_then(|b| read_palette(&b, format)) {
+                                    Ok(colours) => {
+                                        let (fitted, dropped) = fit_palette(&colours);
+
+                                        match pal_ovr {
+                                            true => (ovr_palette, ovr_edited) = (fitted, true),
+                                            false => {
+                                                (palette, palette_edited) = (fitted, true);
+                                                black_pal = palette[0];
+                                                map_tex = None;
+                                                screen_tex = None;
+                                            }
+                                        }
+
+                                        let shown = match pal_ovr {
+                                            true => &ovr_palette,
+                                            false => &palette,
+                                        };
+
+                                        pal_hsv = rgb_to_hsv(shown[pal_sel]);
+
+                                        error_msg = match dropped {
+                                            0 => String::new(),
+                                            n => format!("warning: {} has {} colours, the last {} were left out", img_path, colours.len(), n),
+                                        };
+                                        info_msg = format!("imported {}", img_path);
+
+                                        importing = false;
+                                        current_state = "palette";
+                                    },
+                                    Err(e) => {
+                                        error_msg = format!("could not import {} : {}", img_path, e);
+                                        println!("{}", error_msg);
+                                    }
+                                }
+
+                                break
+                            }
+
+                            match fs::read(&img_path).map_err(CartError::from).and_then(|b| Bitmap::decode(&b)) {
+                                Ok(img) if import_into == "screen" => {
+                                    screen = Screen::from_bitmap(&img, &palette, import_dither);
+                                    screen_edited = true;
+                                    screen_tex = None;
+                                    importing = false;
+
+                                    error_msg.clear();
+                                    current_state = "screen";
+                                },
+                                Ok(img) => {
+                                    // only the colours the sheet mode can hold
+
+                                    import_pix = quantise(&img, &palette[..1 << sheet_bpp], import_dither);
+                                    import_src = Some(img);
+                                    import_at = 0;
+
+                                    error_msg.clear();
+                                    current_state = "import";
+                                },
+                                Err(e) => {
+                                    error_msg = format!("could not import {} : {}", img_path, e);
+                                    println!("{}", error_msg);
+                                }
+                            }
+
+                            break
+                        } else if is_cart {
+                            // png carts are told apart from plain images when reading
+                            if !search_path.is_empty() {
                                 file_path = (search_path.clone() + "/" + name).clone();
                             } else {
                                 file_path = name.to_string().clone();
@@ -493,7 +574,7 @@ async fn main() {
 
                     draw_rectangle(49.0, ypos - txt_size.height - 1.0, txt_size.width + 2.0, txt_size.height + 3.0, sel_col);
 
-                    draw_text(&name, 50.0, ypos, 25.0, txt_col);
+                    draw_text(name, 50.0, ypos, 25.0, txt_col);
                 }
 
                 draw_text("Direct Import", 600.0, 100.0, 25.0, GREEN);
@@ -503,90 +584,79 @@ async fn main() {
             },
             "read_file" => {
                 draw_text(&("Reading ".to_owned() + file_path.as_str()), 50.0, 50.0, 25.0, WHITE);
-                // create a vec of chunks from a .tic
+                // load the .tic as a cartridge
 
-                chunks = deconstruct_tic(file_path.clone().into());
+                cart = match Cartridge::load(&file_path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        error_msg = format!("could not read {} : {}", file_path, e);
+                        println!("{}", error_msg);
 
-                // see if the default palette (and waveforms) should be loaded
+                        current_state = "open";
 
-                let default : bool = find(chunks.clone(), "Default".into());
+                        next_frame().await;
+                        continue
+                    }
+                };
 
-                println!("{default}");
+                error_msg.clear();
 
-                if default {
-                    chunks = replace(
-                        chunks.clone(),
-                        Chunk{
-                            bank : 0,
-                            data : DEFAULT_PALETTE.to_vec().clone(),
-                            name : "Palette".to_string(),
-                        }
-                    );
+                for i in cart.chunks() {
+                    println!("{} byte long {} chunk in bank {}", i.data.len(), i.kind, i.bank);
                 }
 
                 // extract tiles, sprites and palette
-
-                let mut col_ind : u8 = 0;
-                let mut cur_col : [u8; 3] = [0, 0, 0];
-
-                let mut cur_tile : Vec<u8> = vec![];
-
-                let mut cur_spr : Vec<u8> = vec![];
-
-                for imp in IMPORTANT {
-                    let chunk_imp = extract(chunks.clone(), imp.into());
-
-                    let data_imp = chunk_imp.data;
-                    let bank_imp = chunk_imp.bank;
-                    let type_imp = chunk_imp.name;
-
-                    println!("{} byte long {} chunk in bank {}", data_imp.len(), type_imp, bank_imp);
-
-                    if data_imp.len() > 0 {
-                        for k in data_imp {
-
-                            let high : u8 = (k & 0b11110000) >> 4;
-                            let low : u8 = k & 0b00001111;
-
-                            match type_imp.as_str() {
-                                "Tiles" => {
-                                    //print!("{} {} ", low, high);
-                                    cur_tile.push(low);
-                                    cur_tile.push(high);
-
-                                    if cur_tile.len() == 64 {
-                                        tiles.push(cur_tile.clone());
-                                        cur_tile.clear()
-                                    }
-                                },
-                                "Sprites" => {
-                                    //print!("{} {} ", low, high);
-                                    cur_spr.push(low);
-                                    cur_spr.push(high);
-
-                                    if cur_spr.len() == 64 {
-                                        sprites.push(cur_spr.clone());
-                                        cur_spr.clear()
-                                    }
-                                },
-                                _ => {
-                                    //print!("{} ", k);
-                                    cur_col[col_ind as usize] = k;
-                                    col_ind += 1;
-                                    if col_ind == 3 {
-                                        palette.push((
-                                            cur_col[0],
-                                            cur_col[1],
-                                            cur_col[2]
-                                        ));
-                                        col_ind = 0;
-                                    }
-                                },
-                            }
-                        }
-                        //print!("\n");
+                // (the default palette is used if the cart has none)
+
+                palette = cart.palette();
+                palette_edited = false;
+                ovr_palette = cart.ovr_palette();
+                ovr_edited = false;
+                tile_banks = (0..MAX_BANKS).map(|b| cart.tiles_in(b)).collect();
+                sprite_banks = (0..MAX_BANKS).map(|b| cart.sprites_in(b)).collect();
+                loaded_tiles = tile_banks.clone();
+                loaded_sprites = sprite_banks.clone();
+                sheet_bank = 0;
+
+                tiles = tile_banks[0].clone();
+                sprites = sprite_banks[0].clone();
+                bpp_key = None;
+
+                history = History::default();
+                (pal_mark, ovr_mark) = (palette.clone(), ovr_palette.clone());
+                map_banks = (0..MAX_BANKS).map(|b| cart.map_in(b)).collect();
+                map_edited = vec![false; MAX_BANKS as usize];
+                flag_banks = (0..MAX_BANKS).map(|b| cart.flags_in(b)).collect();
+                flags_edited = vec![false; MAX_BANKS as usize];
+
+                map = map_banks[0].clone();
+                flags = flag_banks[0].clone();
+
+                waves = cart.waves();
+                waves_edited = false;
+                sounds = cart.sounds();
+                sounds_edited = false;
+                patterns = cart.patterns();
+                patterns_edited = false;
+                music = cart.music();
+                music_edited = false;
+                screen = cart.screen();
+                screen_edited = false;
+
+                // code that can't be read is left alone, the editor won't open on it
+
+                code_error.clear();
+
+                code_buf = match cart.code() {
+                    Ok(c) => TextBuffer::new(&c),
+                    Err(e) => {
+                        code_error = format!("could not read the code : {}", e);
+                        error_msg = code_error.clone();
+                        TextBuffer::new("")
                     }
-                }
+                };
+                code_edited = false;
+                code_scroll = 0;
 
                 // il love \x1B[38;2;{};{};{}m{}\x1B[0m
 
@@ -594,7 +664,7 @@ async fn main() {
                 for i in &palette {
                     println!("\x1B[38;2;{};{};{}m#{:x}{:x}{:x}\x1B[0m", i.0, i.1, i.2, i.0, i.1, i.2);
                 }
-                print!("\n");
+                println!();
 
                 println!("Tiles");
                 for i in &tiles {
@@ -604,9 +674,9 @@ async fn main() {
 
                             print!("\x1B[38;2;{};{};{}m{:0>2}\x1B[0m", col.0, col.1, col.2, i[k + y*8]);
                         }
-                        print!("\n");
+                        println!();
                     }
-                    print!("\n")
+                    println!()
                 }
 
                 println!("Sprites");
@@ -617,9 +687,9 @@ async fn main() {
 
                             print!("\x1B[38;2;{};{};{}m{:0>2}\x1B[0m", col.0, col.1, col.2, i[k + y*8]);
                         }
-                        print!("\n");
+                        println!();
                     }
-                    print!("\n")
+                    println!()
                 }
 
                 black_pal = palette[0];
@@ -627,28 +697,87 @@ async fn main() {
                 current_state = "main";
             },
             "main" => {
-                draw_rectangle(OFF_X , OFF_Y , ALL_SIDE_LENGTH , ALL_SIDE_LENGTH, color_u8!(255, 255, 255, 125));
-                draw_rectangle(OFF_X , OFF_Y , ALL_SIDE_LENGTH, ALL_SIDE_LENGTH, color_u8!(black_pal.0, black_pal.1, black_pal.2, 125));
-
                 if is_key_pressed(KeyCode::Tab) {
                     show_spr = !show_spr;
                 }
 
-                for id in 0..draw.len() {
-                    let i = &draw[id];
+                if is_key_pressed(KeyCode::I) {
+                    importing = true;
+                    import_into = "sheet";
+                    current_state = "open";
+                }
 
-                    for y in 0..8 {
-                        for k in 0..8 {
-                            let col = palette[i[k + y*8] as usize];
-                            let my_col = color_u8!(col.0, col.1, col.2, 255);
+                if is_key_pressed(KeyCode::M) {
+                    map_tex = None;
+                    current_state = "map";
+                }
+
+                if is_key_pressed(KeyCode::C) && !code_error.is_empty() {
+                    error_msg = code_error.clone();
+                } else if is_key_pressed(KeyCode::C) {
+                    // don't let the C end up in the code
+
+                    while get_char_pressed().is_some() {}
+
+                    code_prompt = "";
+                    current_state = "code";
+                }
+
+                if is_key_pressed(KeyCode::W) {
+                    current_state = "waves";
+                }
+
+                if is_key_pressed(KeyCode::S) {
+                    current_state = "sfx";
+                }
+
+                if is_key_pressed(KeyCode::U) {
+                    current_state = "music";
+                }
+
+                if is_key_pressed(KeyCode::V) {
+                    screen_tex = None;
+                    current_state = "screen";
+                }
+
+                if is_key_pressed(KeyCode::P) {
+                    pal_ovr = false;
+                    pal_hsv = rgb_to_hsv(palette[pal_sel]);
+                    pal_hex.clear();
+
+                    // chars typed elsewhere are still queued up
+
+                    while get_char_pressed().is_some() {}
+
+                    pal_back = "main";
+                    current_state = "palette";
+                }
+
+                // flags belong to 4bpp cells, so they are only edited there
+
+                if is_key_pressed(KeyCode::F) && sheet_bpp == 4 {
+                    flag_mode = !flag_mode;
+
+                    (sel_x, sel_y) = (-16.0 * PIX_SIZE, -16.0 * PIX_SIZE);
+                    (sel_w, sel_h) = (SPR_SIDE_LENGTH, SPR_SIDE_LENGTH);
+                }
+
+                draw_sheet(draw, &palette, black_pal);
+
+                // flags are stored tiles first, then sprites
 
-                            let sx = ((id as f32)%16.0)*PIX_SIZE*8.0;
-                            let sy = ((id as f32)/16.0).floor()*PIX_SIZE*8.0;
+                let flag_base = match show_spr {
+                    true => SPRITE_FLAGS,
+                    false => 0,
+                };
 
-                            let px = OFF_X + (k as f32)*PIX_SIZE + sx;
-                            let py = OFF_Y + (y as f32)*PIX_SIZE + sy;
+                if let Some(f) = flag_overlay {
+                    for cell in 0..256 {
+                        if flags.get(flag_base + cell, f) {
+                            let px = OFF_X + ((cell % 16) as f32) * SPR_SIDE_LENGTH;
+                            let py = OFF_Y + ((cell / 16) as f32) * SPR_SIDE_LENGTH;
 
-                            draw_rectangle(px, py, PIX_SIZE, PIX_SIZE, my_col);
+                            draw_rectangle(px + SPR_SIDE_LENGTH - 10.0, py + 2.0, 8.0, 8.0, FLAG_COLOURS[f as usize]);
                         }
                     }
                 }
@@ -662,14 +791,13 @@ async fn main() {
                         if px >= mx.floor() - SPR_SIDE_LENGTH
                         && py >= my.floor() - SPR_SIDE_LENGTH
                         && px <= mx.floor()
-                        && py <= my.floor() {
-                            if current_press_l {
-                                if last_press_l {
-                                    (sel_w, sel_h) = (px - sel_x + SPR_SIDE_LENGTH, py - sel_y + SPR_SIDE_LENGTH);
-                                } else {
-                                    (sel_x, sel_y) = (px, py);
-                                    (sel_w, sel_h) = (SPR_SIDE_LENGTH, SPR_SIDE_LENGTH);
-                                }
+                        && py <= my.floor()
+                        && current_press_l {
+                            if last_press_l {
+                                (sel_w, sel_h) = (px - sel_x + SPR_SIDE_LENGTH, py - sel_y + SPR_SIDE_LENGTH);
+                            } else {
+                                (sel_x, sel_y) = (px, py);
+                                (sel_w, sel_h) = (SPR_SIDE_LENGTH, SPR_SIDE_LENGTH);
                             }
                         }
                     }
@@ -677,10 +805,67 @@ async fn main() {
 
                 draw_rectangle_lines(sel_x - SELECTION_THICK / 2.0, sel_y - SELECTION_THICK / 2.0, sel_w + SELECTION_THICK, sel_h + SELECTION_THICK, SELECTION_THICK, WHITE);
 
-                if !current_press_l && sel_x > 0.0 && sel_y > 0.0 {
+                if !current_press_l && sel_x > 0.0 && sel_y > 0.0 && !flag_mode {
                     current_state = "edit";
                 }
 
+                if flag_mode {
+                    // the selection stays put while editing flags
+
+                    let mut ids : Vec<usize> = vec![];
+
+                    for cell in 0..256 {
+                        let px = OFF_X + ((cell % 16) as f32) * SPR_SIDE_LENGTH;
+                        let py = OFF_Y + ((cell / 16) as f32) * SPR_SIDE_LENGTH;
+
+                        if px >= sel_x && py >= sel_y
+                        && px < sel_x + sel_w && py < sel_y + sel_h {
+                            ids.push(flag_base + cell);
+                        }
+                    }
+
+                    draw_text("Flags: select cells, click a bit to toggle it", 10.0, 70.0, 20.0, WHITE);
+                    draw_text("0-7 show a flag on the sheet, F done", 10.0, 90.0, 20.0, WHITE);
+
+                    for f in 0..8u8 {
+                        let fy = 110.0 + (f as f32) * 30.0;
+
+                        let set = flags.count(&ids, f);
+
+                        if set == ids.len() && !ids.is_empty() {
+                            draw_rectangle(20.0, fy, 20.0, 20.0, FLAG_COLOURS[f as usize]);
+                        } else if set > 0 {
+                            draw_rectangle(25.0, fy + 5.0, 10.0, 10.0, FLAG_COLOURS[f as usize]);
+                        }
+
+                        draw_rectangle_lines(20.0, fy, 20.0, 20.0, 2.0, FLAG_COLOURS[f as usize]);
+
+                        let shown = match flag_overlay == Some(f) {
+                            true => " (shown)",
+                            false => "",
+                        };
+
+                        draw_text(&format!("flag {}{}", f, shown), 50.0, fy + 16.0, 20.0, WHITE);
+
+                        if (20.0..200.0).contains(&mx) && (fy..fy + 20.0).contains(&my)
+                        && current_press_l && !last_press_l && !ids.is_empty() {
+                            flags.toggle_all(&ids, f);
+                            flags_edited[sheet_bank] = true;
+                        }
+                    }
+
+                    let digits = [KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7];
+
+                    for (f, &key) in digits.iter().enumerate() {
+                        if is_key_pressed(key) {
+                            flag_overlay = match flag_overlay == Some(f as u8) {
+                                true => None,
+                                false => Some(f as u8),
+                            };
+                        }
+                    }
+                }
+
                 to_draw.clear();
 
                 for x in 0..16 {
@@ -691,7 +876,7 @@ async fn main() {
                         if px >= sel_x && py >= sel_y
                         && px < sel_x + sel_w && py < sel_y + sel_h {
                             if x + y * 16 < draw.len() {
-                                to_draw.push(draw[(x + y * 16) as usize].clone());
+                                to_draw.push(draw[x + y * 16].clone());
                             } else {
                                 to_draw.push(EMPTY_SPR.to_vec().clone());
                             }
@@ -699,6 +884,10 @@ async fn main() {
                     }
                 }
 
+                // what the selection starts out as, for the undo history
+
+                sel_mark = to_draw.clone();
+
                 //draw_rectangle(0.0, 0.0, 50.0, 50.0, WHITE);
                 /*draw_texture_ex(&save, 0.0, 0.0, WHITE, DrawTextureParams {
                     dest_size : Some(Vec2 {
@@ -714,83 +903,1236 @@ async fn main() {
 
                 draw_img(save_image.to_vec());
 
-                if mx < PIX_SIZE * 8.0 && my < PIX_SIZE * 8.0 && current_press_l && !last_press_l {
-                    current_state = "saving";
-                }
-            },
-            "edit" => {
+                // export the whole sheet as a png
 
-                draw_rectangle(PALETTE_SIZE - SELECTION_THICK, SCREEN_HEIGHT / 2.0 - 8.0 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE * 16.0 + SELECTION_THICK * 2.0, WHITE);
-                draw_rectangle(PALETTE_SIZE - SELECTION_THICK, SCREEN_HEIGHT / 2.0 + 9.0 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE + SELECTION_THICK * 2.0, WHITE);
-                draw_rectangle(PALETTE_SIZE * 1.5 - SELECTION_THICK, SCREEN_HEIGHT / 2.0 + 9.5 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE + SELECTION_THICK * 2.0, WHITE);
+                if is_key_pressed(KeyCode::T) {
+                    export_alpha = !export_alpha;
+                }
 
-                for c in 0..palette.len() {
-                    let col = palette[c];
+                if is_key_pressed(KeyCode::Equal) && export_scale < 16 {
+                    export_scale += 1;
+                } else if is_key_pressed(KeyCode::Minus) && export_scale > 1 {
+                    export_scale -= 1;
+                }
 
-                    let cy = SCREEN_HEIGHT / 2.0 - 8.0 * PALETTE_SIZE + (c as f32) * PALETTE_SIZE;
+                draw_text(&format!("E export png (x{}, T transparency {})  C code, W waves, S sfx, U music, V cover, P palette, Ctrl+Z/Y undo/redo", export_scale, export_alpha), 10.0, SCREEN_HEIGHT - 30.0, 20.0, WHITE);
 
-                    let my_col = color_u8!(col.0, col.1, col.2, 255);
+                if is_key_pressed(KeyCode::E) {
+                    let mut what = match (show_spr, sheet_bank) {
+                        (true, 0) => "sprites".to_string(),
+                        (false, 0) => "tiles".to_string(),
+                        (true, b) => format!("sprites_bank{}", b),
+                        (false, b) => format!("tiles_bank{}", b),
+                    };
 
-                    if mx < PALETTE_SIZE * 2.0 + SELECTION_THICK
-                    && my >= cy && my < cy + PALETTE_SIZE {
-                        if current_press_l && !last_press_l {
-                            primary = c;
-                        } else if current_press_r && !last_press_r {
-                            secondary = c;
-                        }
+                    if sheet_bpp != 4 {
+                        what += &format!("_{}bpp_page{}", sheet_bpp, sheet_page);
                     }
 
-                    draw_rectangle(PALETTE_SIZE, cy, PALETTE_SIZE, PALETTE_SIZE, my_col);
-                }
-
-                let col = palette[secondary];
+                    let img = render_sheet(draw, &palette, export_alpha, export_scale);
 
-                draw_rectangle(PALETTE_SIZE, SCREEN_HEIGHT / 2.0 + 9.0 * PALETTE_SIZE, PALETTE_SIZE, PALETTE_SIZE, color_u8!(col.0, col.1, col.2, 255));
+                    (error_msg, info_msg) = export_png(&img, &export_path(&file_path, &what, "png"));
+                }
 
-                let col = palette[primary];
+                // B cycles 4/2/1 bpp, , and . flip through the pages below 4bpp
 
-                draw_rectangle(PALETTE_SIZE * 1.5, SCREEN_HEIGHT / 2.0 + 9.5 * PALETTE_SIZE, PALETTE_SIZE, PALETTE_SIZE, color_u8!(col.0, col.1, col.2, 255));
+                if is_key_pressed(KeyCode::B) {
+                    let at = BPP_MODES.iter().position(|&b| b == sheet_bpp).unwrap_or(0);
 
-                let mult : f32;
+                    sheet_bpp = BPP_MODES[(at + 1) % BPP_MODES.len()];
+                    sheet_page = 0;
+                    flag_mode = false;
+                }
 
-                if (SCREEN_HEIGHT - 6.0 * PALETTE_SIZE) / sel_h < (SCREEN_WIDTH - 6.0 * PALETTE_SIZE) / sel_w {
-                    mult = (SCREEN_HEIGHT - 6.0 * PALETTE_SIZE) / sel_h;
-                } else {
-                    mult = (SCREEN_WIDTH - 6.0 * PALETTE_SIZE) / sel_w;
+                if is_key_pressed(KeyCode::Comma) {
+                    sheet_page = sheet_page.saturating_sub(1);
+                } else if is_key_pressed(KeyCode::Period) {
+                    sheet_page = (sheet_page + 1).min(bpp_pages(sheet_bpp) - 1);
                 }
 
-                let (mut drax, mut dray) : (usize, usize) = (0, 0);
+                let mode = match sheet_bpp {
+                    4 => "4bpp  B mode".to_string(),
+                    b => format!("{}bpp page {}/{}  B mode, ,/. page", b, sheet_page + 1, bpp_pages(b)),
+                };
 
-                let mut hover : bool = false;
+                draw_text(&mode, OFF_X + (MAX_BANKS as f32) * 28.0 + 10.0, 27.0, 20.0, WHITE);
 
-                for idx in 0..to_draw.len() {
-                    let l = &mut to_draw[idx as usize];
+                // bank selector above the sheet, [ and ] step through the banks
 
-                    for id in 0..l.len() {
-                        let i = &mut l[id as usize];
+                let mut bank = sheet_bank;
 
-                        let col = palette[*i as usize];
-                        let my_col = color_u8!(col.0, col.1, col.2, 255);
+                if is_key_pressed(KeyCode::LeftBracket) { bank = bank.saturating_sub(1); }
+                if is_key_pressed(KeyCode::RightBracket) { bank = (bank + 1).min(MAX_BANKS as usize - 1); }
 
-                        let x = (id as f32)%8.0 * PIX_SIZE * mult;
-                        let y = ((id as f32)/8.0).floor() * PIX_SIZE * mult;
+                for b in 0..MAX_BANKS as usize {
+                    let bx = OFF_X + (b as f32) * 28.0;
 
-                        let ofx = ((idx as f32)/(sel_h / SPR_SIDE_LENGTH)).floor() * SPR_SIDE_LENGTH * mult;
-                        let ofy = (idx as f32)%(sel_h / SPR_SIDE_LENGTH) * SPR_SIDE_LENGTH * mult;
+                    if (bx..bx + 24.0).contains(&mx) && (8.0..32.0).contains(&my) && current_press_l && !last_press_l {
+                        bank = b;
+                    }
 
-                        let bx = (SCREEN_WIDTH - sel_w * mult) / 2.0;
-                        let by = (SCREEN_HEIGHT - sel_h * mult) / 2.0;
+                    let (fill, text) = match b == sheet_bank {
+                        true => (WHITE, BLACK),
+                        false => (DARKGRAY, WHITE),
+                    };
 
-                        draw_rectangle(x + ofx + bx, y + ofy + by, PIX_SIZE * mult, PIX_SIZE * mult, my_col);
+                    draw_rectangle(bx, 8.0, 24.0, 24.0, fill);
+                    draw_text(&b.to_string(), bx + 7.0, 27.0, 20.0, text);
+                }
 
-                        if mx >= x + ofx + bx
-                        && mx < x + ofx + bx + PIX_SIZE * mult
-                        && my >= y + ofy + by
-                        && my < y + ofy + by + PIX_SIZE * mult {
-                            hover = true;
+                if bank != sheet_bank {
+                    tile_banks[sheet_bank] = std::mem::replace(&mut tiles, tile_banks[bank].clone());
+                    sprite_banks[sheet_bank] = std::mem::replace(&mut sprites, sprite_banks[bank].clone());
+                    map_banks[sheet_bank] = std::mem::replace(&mut map, map_banks[bank].clone());
+                    flag_banks[sheet_bank] = std::mem::replace(&mut flags, flag_banks[bank].clone());
 
-                            if current_press_l || current_press_r {
-                                drax = idx;
+                    sheet_bank = bank;
+                    map_tex = None;
+                }
+
+                if mx < PIX_SIZE * 8.0 && my < PIX_SIZE * 8.0 && current_press_l && !last_press_l {
+                    current_state = "saving";
+                }
+            },
+            "map" => {
+                let tile_px = 8.0 * map_zoom;
+
+                let view_w = (SCREEN_WIDTH / tile_px).ceil() as usize;
+                let view_h = ((SCREEN_HEIGHT - MAP_TOP) / tile_px).ceil() as usize;
+
+                if is_key_pressed(KeyCode::S) {
+                    picking = !picking;
+                }
+
+                if is_key_pressed(KeyCode::Escape) {
+                    match picking {
+                        true => picking = false,
+                        false => current_state = "main",
+                    }
+                }
+
+                if picking {
+                    // drag over the sheet to pick a (multi) tile stamp
+
+                    draw_sheet(&tiles, &palette, black_pal);
+
+                    if let Some(cell) = sheet_cell(mx, my) {
+                        if current_press_l && !last_press_l {
+                            pick_from = cell;
+                        }
+
+                        if current_press_l {
+                            stamp = Stamp::from_sheet(pick_from, cell);
+                        }
+                    }
+
+                    if !current_press_l && last_press_l {
+                        picking = false;
+                    }
+
+                    let first = stamp.tiles[0] as usize;
+
+                    draw_rectangle_lines(OFF_X + ((first % 16) as f32) * SPR_SIDE_LENGTH, OFF_Y + ((first / 16) as f32) * SPR_SIDE_LENGTH, (stamp.width as f32) * SPR_SIDE_LENGTH, (stamp.height as f32) * SPR_SIDE_LENGTH, 2.0, WHITE);
+
+                    draw_text("Drag to pick tiles, Esc to go back", 10.0, 20.0, 20.0, WHITE);
+                } else {
+                    // move around, one tile or (with shift) one screen at a time
+
+                    let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+                    let (step_x, step_y) = match shift {
+                        true => (SCREEN_TILES_W, SCREEN_TILES_H),
+                        false => (1, 1),
+                    };
+
+                    let (old_x, old_y, old_zoom) = (map_x, map_y, map_zoom);
+
+                    if is_key_pressed(KeyCode::Left) { map_x = map_x.saturating_sub(step_x); }
+                    if is_key_pressed(KeyCode::Right) { map_x += step_x; }
+                    if is_key_pressed(KeyCode::Up) { map_y = map_y.saturating_sub(step_y); }
+                    if is_key_pressed(KeyCode::Down) { map_y += step_y; }
+
+                    // 1-8 jump to a screen along the row, or down the column with shift
+
+                    let digits = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8];
+
+                    for (n, &key) in digits.iter().enumerate() {
+                        if is_key_pressed(key) {
+                            match shift {
+                                true => map_y = n * SCREEN_TILES_H,
+                                false => map_x = n * SCREEN_TILES_W,
+                            }
+                        }
+                    }
+
+                    let mw = mouse_wheel().1;
+
+                    if mw > 0.0 && map_zoom < 4.0 {
+                        map_zoom += 1.0;
+                    } else if mw < 0.0 && map_zoom > 1.0 {
+                        map_zoom -= 1.0;
+                    }
+
+                    map_x = map_x.min(MAP_WIDTH.saturating_sub(view_w));
+                    map_y = map_y.min(MAP_HEIGHT.saturating_sub(view_h));
+
+                    if (old_x, old_y, old_zoom) != (map_x, map_y, map_zoom) {
+                        map_tex = None;
+                    }
+
+                    // paint with the stamp, right click picks the tile under the mouse
+
+                    let tx = map_x + (mx / tile_px) as usize;
+                    let ty = map_y + ((my - MAP_TOP).max(0.0) / tile_px) as usize;
+
+                    let on_map = my >= MAP_TOP && tx < MAP_WIDTH && ty < MAP_HEIGHT;
+
+                    if on_map && current_press_l {
+                        map.stamp(tx, ty, &stamp);
+                        map_edited[sheet_bank] = true;
+                        map_tex = None;
+                    } else if on_map && current_press_r && !last_press_r {
+                        stamp = Stamp::single(map.get(tx, ty));
+                    }
+
+                    // the visible part of the map is only redrawn when something changed
+
+                    let tex = map_tex.get_or_insert_with(|| {
+                        let img = map.render(&tiles, &palette, map_x, map_y, view_w, view_h);
+                        let tex = Texture2D::from_rgba8(img.width as u16, img.height as u16, &img.rgba);
+
+                        tex.set_filter(FilterMode::Nearest);
+
+                        tex
+                    });
+
+                    draw_texture_ex(tex, 0.0, MAP_TOP, WHITE, DrawTextureParams {
+                        dest_size : Some(vec2(tex.width() * map_zoom, tex.height() * map_zoom)),
+                        ..Default::default()
+                    });
+
+                    if on_map {
+                        let hx = ((tx - map_x) as f32) * tile_px;
+                        let hy = MAP_TOP + ((ty - map_y) as f32) * tile_px;
+
+                        draw_rectangle_lines(hx, hy, (stamp.width as f32) * tile_px, (stamp.height as f32) * tile_px, 2.0, WHITE);
+                    }
+
+                    draw_text(&format!("Map bank {} {},{} (screen {},{}) x{}  S pick tiles, arrows/1-8 move, wheel zoom, Esc back", sheet_bank, map_x, map_y, map_x / SCREEN_TILES_W, map_y / SCREEN_TILES_H, map_zoom), 10.0, 20.0, 20.0, WHITE);
+                }
+            },
+            "code" => {
+                let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+                let page = ((SCREEN_HEIGHT - CODE_TOP - 40.0) / CODE_LINE) as usize;
+
+                if ctrl && is_key_pressed(KeyCode::F) {
+                    code_prompt = "find";
+                }
+
+                if ctrl && is_key_pressed(KeyCode::R) {
+                    code_prompt = "replace";
+                }
+
+                if code_prompt.is_empty() {
+                    if is_key_pressed(KeyCode::Escape) {
+                        current_state = "main";
+                    }
+
+                    // typing, control keys are handled below
+
+                    while let Some(c) = get_char_pressed() {
+                        if ctrl || c.is_control() {
+                            continue
+                        }
+
+                        code_buf.insert(c);
+                        code_edited = true;
+                    }
+
+                    if is_key_pressed(KeyCode::Enter) { code_buf.newline(); code_edited = true; }
+                    if is_key_pressed(KeyCode::Backspace) { code_buf.backspace(); code_edited = true; }
+                    if is_key_pressed(KeyCode::Delete) { code_buf.delete(); code_edited = true; }
+                    if is_key_pressed(KeyCode::Tab) { code_buf.insert_str(&" ".repeat(TAB_WIDTH)); code_edited = true; }
+
+                    if is_key_pressed(KeyCode::Left) { code_buf.left(); }
+                    if is_key_pressed(KeyCode::Right) { code_buf.right(); }
+                    if is_key_pressed(KeyCode::Up) { code_buf.up(1); }
+                    if is_key_pressed(KeyCode::Down) { code_buf.down(1); }
+                    if is_key_pressed(KeyCode::PageUp) { code_buf.up(page); }
+                    if is_key_pressed(KeyCode::PageDown) { code_buf.down(page); }
+                    if is_key_pressed(KeyCode::Home) { code_buf.home(); }
+                    if is_key_pressed(KeyCode::End) { code_buf.end(); }
+                } else {
+                    // find (and replace) prompt, Tab switches field
+
+                    if is_key_pressed(KeyCode::Escape) {
+                        code_prompt = "";
+                    }
+
+                    if is_key_pressed(KeyCode::Tab) {
+                        code_prompt = match code_prompt {
+                            "replace" => "with",
+                            "with" => "replace",
+                            other => other,
+                        };
+                    }
+
+                    let field = match code_prompt {
+                        "with" => &mut code_with,
+                        _ => &mut code_find,
+                    };
+
+                    while let Some(c) = get_char_pressed() {
+                        if !ctrl && !c.is_control() {
+                            field.push(c);
+                        }
+                    }
+
+                    if is_key_pressed(KeyCode::Backspace) {
+                        field.pop();
+                    }
+
+                    if is_key_pressed(KeyCode::Enter) {
+                        match code_prompt {
+                            "find" => match code_buf.find(&code_find) {
+                                true => info_msg.clear(),
+                                false => info_msg = format!("{} not found", code_find),
+                            },
+                            _ => {
+                                let count = code_buf.replace_all(&code_find, &code_with);
+
+                                if count > 0 {
+                                    code_edited = true;
+                                }
+
+                                info_msg = format!("replaced {} times", count);
+                                code_prompt = "";
+                            }
+                        }
+                    }
+                }
+
+                // keep the cursor on screen, the wheel scrolls freely
+
+                let mw = mouse_wheel().1;
+
+                if mw > 0.0 {
+                    code_scroll = code_scroll.saturating_sub(3);
+                } else if mw < 0.0 {
+                    code_scroll = (code_scroll + 3).min(code_buf.lines.len().saturating_sub(1));
+                }
+
+                if mw == 0.0 {
+                    if code_buf.line < code_scroll {
+                        code_scroll = code_buf.line;
+                    } else if code_buf.line >= code_scroll + page {
+                        code_scroll = code_buf.line + 1 - page;
+                    }
+                }
+
+                // the comment state has to be carried from the top of the file
+
+                let mut in_comment = false;
+
+                for (n, line) in code_buf.lines.iter().enumerate() {
+                    let (tokens, next) = highlight_line(line, in_comment);
+                    in_comment = next;
+
+                    if n < code_scroll {
+                        continue
+                    }
+
+                    if n >= code_scroll + page {
+                        break
+                    }
+
+                    let y = CODE_TOP + ((n - code_scroll + 1) as f32) * CODE_LINE;
+
+                    draw_text(&format!("{:>4}", n + 1), 5.0, y, 20.0, GRAY);
+
+                    let chars : Vec<char> = line.chars().collect();
+                    let mut x = CODE_GUTTER;
+
+                    for t in tokens {
+                        let text : String = chars[t.start..t.end].iter().collect();
+
+                        let col = match t.kind {
+                            TokenKind::Keyword => SKYBLUE,
+                            TokenKind::Number => ORANGE,
+                            TokenKind::Str => GREEN,
+                            TokenKind::Comment => GRAY,
+                            TokenKind::Symbol => YELLOW,
+                            _ => WHITE,
+                        };
+
+                        draw_text(&text, x, y, 20.0, col);
+                        x += measure_text(&text, None, 20, 1.0).width;
+                    }
+
+                    if n == code_buf.line {
+                        let before : String = chars[..code_buf.col].iter().collect();
+                        let cx = CODE_GUTTER + measure_text(&before, None, 20, 1.0).width;
+
+                        draw_line(cx, y - CODE_LINE + 4.0, cx, y + 4.0, 2.0, WHITE);
+                    }
+                }
+
+                let edited = match code_edited {
+                    true => "*",
+                    false => "",
+                };
+
+                draw_text(&format!("Code{} {}:{}  Ctrl+F find, Ctrl+R replace, Esc back", edited, code_buf.line + 1, code_buf.col + 1), 10.0, 20.0, 20.0, WHITE);
+
+                match code_prompt {
+                    "find" => { draw_text(&format!("find: {}_  (Enter next, Esc close)", code_find), 10.0, 40.0, 20.0, YELLOW); },
+                    "replace" => { draw_text(&format!("replace: {}_  with: {}  (Tab switch, Enter all)", code_find, code_with), 10.0, 40.0, 20.0, YELLOW); },
+                    "with" => { draw_text(&format!("replace: {}  with: {}_  (Tab switch, Enter all)", code_find, code_with), 10.0, 40.0, 20.0, YELLOW); },
+                    _ => {},
+                }
+            },
+            "waves" => {
+                let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+                if is_key_pressed(KeyCode::Escape) {
+                    current_state = "main";
+                }
+
+                if is_key_pressed(KeyCode::Up) { wave_sel = wave_sel.saturating_sub(1); }
+                if is_key_pressed(KeyCode::Down) { wave_sel = (wave_sel + 1).min(WAVE_COUNT - 1); }
+
+                // 1-4 fill the wave with a preset
+
+                let presets = [(KeyCode::Key1, Preset::Square), (KeyCode::Key2, Preset::Saw), (KeyCode::Key3, Preset::Triangle), (KeyCode::Key4, Preset::Noise)];
+
+                for (key, kind) in presets {
+                    if is_key_pressed(key) {
+                        waves.waves[wave_sel] = preset(kind, (get_time() * 1000.0) as u32);
+                        waves_edited = true;
+                    }
+                }
+
+                if ctrl && is_key_pressed(KeyCode::C) {
+                    wave_clip = Some(waves.waves[wave_sel].clone());
+                    info_msg = format!("copied wave {}", wave_sel);
+                }
+
+                if ctrl && is_key_pressed(KeyCode::V) {
+                    if let Some(clip) = &wave_clip {
+                        waves.waves[wave_sel] = clip.clone();
+                        waves_edited = true;
+                    }
+                }
+
+                // P writes a second of the wave as an A4 to a .wav next to the cart
+
+                if is_key_pressed(KeyCode::P) {
+                    let samples = preview(&waves.waves[wave_sel], 440.0, 1.0, SAMPLE_RATE);
+
+                    (error_msg, info_msg) = export_wav(&samples, &export_path(&file_path, &format!("wave{}", wave_sel), "wav"));
+                }
+
+                // the slots on the left, click one to edit it
+
+                for (i, wave) in waves.waves.iter().enumerate() {
+                    let (sx, sy) = (10.0, 40.0 + (i as f32) * 32.0);
+
+                    if (sx..sx + 128.0).contains(&mx) && (sy..sy + 30.0).contains(&my) && current_press_l && !last_press_l {
+                        wave_sel = i;
+                    }
+
+                    let frame = match i == wave_sel {
+                        true => WHITE,
+                        false => DARKGRAY,
+                    };
+
+                    draw_rectangle_lines(sx, sy, 128.0, 30.0, 2.0, frame);
+
+                    for (k, &level) in wave.iter().enumerate() {
+                        let h = (level as f32 + 1.0) * 1.5;
+
+                        draw_rectangle(sx + 16.0 + (k as f32) * 3.5, sy + 27.0 - h, 3.0, h, SKYBLUE);
+                    }
+
+                    draw_text(&format!("{:>2}", i), sx + 2.0, sy + 20.0, 16.0, WHITE);
+                }
+
+                // the selected wave as bars, painted by dragging over them
+
+                let graph_h = (WAVE_MAX as f32 + 1.0) * WAVE_BAR_H;
+
+                let bar = ((mx - WAVE_X) / WAVE_BAR_W).floor();
+                let level = WAVE_MAX as f32 - ((my - WAVE_Y) / WAVE_BAR_H).floor();
+
+                if current_press_l && (0.0..WAVE_SAMPLES as f32).contains(&bar) && (0.0..=WAVE_MAX as f32).contains(&level) {
+                    waves.waves[wave_sel][bar as usize] = level as u8;
+                    waves_edited = true;
+                }
+
+                draw_rectangle_lines(WAVE_X, WAVE_Y, WAVE_SAMPLES as f32 * WAVE_BAR_W, graph_h, 2.0, DARKGRAY);
+
+                for (k, &level) in waves.waves[wave_sel].iter().enumerate() {
+                    let h = (level as f32 + 1.0) * WAVE_BAR_H;
+
+                    draw_rectangle(WAVE_X + (k as f32) * WAVE_BAR_W + 1.0, WAVE_Y + graph_h - h, WAVE_BAR_W - 2.0, h, SKYBLUE);
+                }
+
+                draw_text(&format!("Wave {}  drag to draw, 1-4 square/saw/triangle/noise, Ctrl+C/V copy/paste", wave_sel), 10.0, 20.0, 20.0, WHITE);
+                draw_text("P export a .wav preview, Up/Down pick a wave, Esc back", WAVE_X, WAVE_Y + graph_h + 25.0, 20.0, WHITE);
+            },
+            "sfx" => {
+                if is_key_pressed(KeyCode::Escape) {
+                    current_state = "main";
+                }
+
+                if is_key_pressed(KeyCode::LeftBracket) { sfx_sel = sfx_sel.saturating_sub(1); }
+                if is_key_pressed(KeyCode::RightBracket) { sfx_sel = (sfx_sel + 1).min(SFX_COUNT - 1); }
+
+                let sfx = &mut sounds.sfx[sfx_sel];
+                let before = sfx.clone();
+
+                // per sfx fields, each with a key to raise and one to lower it
+
+                if is_key_pressed(KeyCode::Q) && sfx.speed < 3 { sfx.speed += 1; }
+                if is_key_pressed(KeyCode::A) && sfx.speed > -4 { sfx.speed -= 1; }
+                if is_key_pressed(KeyCode::W) && sfx.octave < 7 { sfx.octave += 1; }
+                if is_key_pressed(KeyCode::S) && sfx.octave > 0 { sfx.octave -= 1; }
+                if is_key_pressed(KeyCode::E) && sfx.note < 11 { sfx.note += 1; }
+                if is_key_pressed(KeyCode::D) && sfx.note > 0 { sfx.note -= 1; }
+                if is_key_pressed(KeyCode::R) { sfx.reverse = !sfx.reverse; }
+
+                let tick = ((mx - SFX_X) / SFX_CELL_W).floor();
+                let on_ticks = (0.0..SFX_TICKS as f32).contains(&tick);
+
+                if !current_press_l {
+                    sfx_loop_drag = None;
+                }
+
+                for (i, &env) in ENVELOPES.iter().enumerate() {
+                    let top = SFX_TOP + (i as f32) * SFX_LANE;
+                    let bottom = top + 16.0 * SFX_LEVEL_H;
+                    let (lo, _) = env.range();
+
+                    // drag over a lane to draw the envelope
+
+                    if on_ticks && current_press_l && (top..bottom).contains(&my) {
+                        let level = 15 - ((my - top) / SFX_LEVEL_H) as i8;
+                        sfx.set(env, tick as usize, lo + level);
+                    }
+
+                    // the strip under it sets the loop, press at the start and drag to the end
+
+                    let strip = bottom + 4.0;
+
+                    if on_ticks && current_press_l && !last_press_l && (strip..strip + 12.0).contains(&my) {
+                        sfx_loop_drag = Some((i, (tick as u8).min(15)));
+                    }
+
+                    if let Some((lane, start)) = sfx_loop_drag {
+                        if lane == i && on_ticks {
+                            let size = (tick as u8).saturating_sub(start).min(15);
+                            sfx.loops[env.index()] = Loop { start, size };
+                        }
+                    }
+
+                    draw_text(env.name(), 10.0, top + 20.0, 20.0, WHITE);
+                    draw_rectangle_lines(SFX_X, top, SFX_TICKS as f32 * SFX_CELL_W, bottom - top, 2.0, DARKGRAY);
+
+                    for k in 0..SFX_TICKS {
+                        let h = ((sfx.get(env, k) - lo) as f32 + 1.0) * SFX_LEVEL_H;
+
+                        draw_rectangle(SFX_X + (k as f32) * SFX_CELL_W + 1.0, bottom - h, SFX_CELL_W - 2.0, h, SKYBLUE);
+                    }
+
+                    let l = sfx.loops[env.index()];
+
+                    draw_rectangle_lines(SFX_X, strip, SFX_TICKS as f32 * SFX_CELL_W, 12.0, 1.0, DARKGRAY);
+
+                    if l.size > 0 {
+                        draw_rectangle(SFX_X + (l.start as f32) * SFX_CELL_W, strip, (l.size as f32) * SFX_CELL_W, 12.0, ORANGE);
+                    }
+                }
+
+                if *sfx != before {
+                    sounds_edited = true;
+                }
+
+                // P renders the sfx, as it would sound on its own note, to a .wav next to the cart
+
+                if is_key_pressed(KeyCode::P) {
+                    let samples = render_sfx(&waves, &sounds, sfx_sel, None, SAMPLE_RATE);
+
+                    (error_msg, info_msg) = export_wav(&samples, &export_path(&file_path, &format!("sfx{}", sfx_sel), "wav"));
+                }
+
+                let sfx = &sounds.sfx[sfx_sel];
+
+                draw_text(&format!("SFX {}  [ ] pick, Q/A speed, W/S octave, E/D note, R reverse, P .wav, Esc back", sfx_sel), 10.0, 20.0, 20.0, WHITE);
+                draw_text(&format!("speed {}  note {}{}  reverse {}  (volume 0 is the loudest)", sfx.speed, NOTE_NAMES[sfx.note as usize % 12], sfx.octave, sfx.reverse), 10.0, 40.0, 20.0, YELLOW);
+            },
+            "music" => {
+                let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+                if is_key_pressed(KeyCode::Escape) {
+                    current_state = "main";
+                }
+
+                if is_key_pressed(KeyCode::LeftBracket) { track_sel = track_sel.saturating_sub(1); }
+                if is_key_pressed(KeyCode::RightBracket) { track_sel = (track_sel + 1).min(music.tracks.len() - 1); }
+
+                let track = &mut music.tracks[track_sel];
+                let before = track.clone();
+
+                // shift + arrows change the track settings, plain arrows move the cursor
+
+                if shift {
+                    if is_key_pressed(KeyCode::Up) && track.tempo < 255 { track.tempo += 1; }
+                    if is_key_pressed(KeyCode::Down) && track.tempo > 32 { track.tempo -= 1; }
+                    if is_key_pressed(KeyCode::Right) && track.speed < 31 { track.speed += 1; }
+                    if is_key_pressed(KeyCode::Left) && track.speed > 1 { track.speed -= 1; }
+                    if is_key_pressed(KeyCode::PageUp) && track.rows < PATTERN_ROWS { track.rows += 1; }
+                    if is_key_pressed(KeyCode::PageDown) && track.rows > 1 { track.rows -= 1; }
+                } else {
+                    if is_key_pressed(KeyCode::Up) { row_sel = row_sel.saturating_sub(1); }
+                    if is_key_pressed(KeyCode::Down) { row_sel = (row_sel + 1).min(PATTERN_ROWS - 1); }
+                    if is_key_pressed(KeyCode::Left) { field_sel = field_sel.saturating_sub(1); }
+                    if is_key_pressed(KeyCode::Right) { field_sel = (field_sel + 1).min(CHANNELS * ROW_FIELDS - 1); }
+                    if is_key_pressed(KeyCode::PageUp) { frame_sel = frame_sel.saturating_sub(1); }
+                    if is_key_pressed(KeyCode::PageDown) { frame_sel = (frame_sel + 1).min(FRAME_COUNT - 1); }
+                }
+
+                let channel = field_sel / ROW_FIELDS;
+
+                // , and . pick the pattern the channel plays in this frame
+
+                let slot = &mut track.frames[frame_sel][channel];
+
+                if is_key_pressed(KeyCode::Comma) && *slot > 0 { *slot -= 1; }
+                if is_key_pressed(KeyCode::Period) && (*slot as usize) < PATTERN_COUNT { *slot += 1; }
+
+                let pattern_id = *slot as usize;
+
+                if *track != before {
+                    music_edited = true;
+                }
+
+                // clicking the frame list picks a frame and channel
+
+                for f in 0..FRAME_COUNT {
+                    for c in 0..CHANNELS {
+                        let (fx, fy) = (10.0 + (c as f32) * 40.0, TRACK_TOP + (f as f32) * TRACK_ROW);
+
+                        if (fx..fx + 40.0).contains(&mx) && (fy - TRACK_ROW..fy).contains(&my) && current_press_l && !last_press_l {
+                            frame_sel = f;
+                            field_sel = c * ROW_FIELDS;
+                        }
+                    }
+                }
+
+                // editing the row under the cursor
+
+                if pattern_id > 0 {
+                    let row = &mut patterns.patterns[pattern_id - 1][row_sel];
+                    let before = *row;
+
+                    let step : i32 = match (is_key_pressed(KeyCode::Equal), is_key_pressed(KeyCode::Minus)) {
+                        (true, false) => 1,
+                        (false, true) => -1,
+                        _ => 0,
+                    };
+
+                    let bump = |v: u8, max: u8| (v as i32 + step).clamp(0, max as i32) as u8;
+
+                    match field_sel % ROW_FIELDS {
+                        0 => {
+                            // the bottom row of letters plays like a piano, 1 stops the channel
+
+                            let piano = [KeyCode::Z, KeyCode::S, KeyCode::X, KeyCode::D, KeyCode::C, KeyCode::V, KeyCode::G, KeyCode::B, KeyCode::H, KeyCode::N, KeyCode::J, KeyCode::M];
+
+                            for (n, &key) in piano.iter().enumerate() {
+                                if is_key_pressed(key) {
+                                    row.note = NOTE_FIRST + n as u8;
+
+                                    if before.note < NOTE_FIRST {
+                                        row.octave = 4;
+                                    }
+                                }
+                            }
+
+                            if is_key_pressed(KeyCode::Key1) {
+                                row.note = NOTE_STOP;
+                            }
+
+                            if step != 0 && row.note >= NOTE_FIRST {
+                                row.note = bump(row.note - NOTE_FIRST, 11) + NOTE_FIRST;
+                            }
+                        },
+                        1 => row.octave = bump(row.octave, 7),
+                        2 => row.sfx = bump(row.sfx, 63),
+                        3 => row.command = bump(row.command, 7),
+                        4 => row.param1 = bump(row.param1, 15),
+                        _ => row.param2 = bump(row.param2, 15),
+                    }
+
+                    if is_key_pressed(KeyCode::Delete) {
+                        *row = Row::default();
+                    }
+
+                    if *row != before {
+                        patterns_edited = true;
+                    }
+                }
+
+                if is_key_pressed(KeyCode::P) {
+                    let samples = render_track(&waves, &sounds, &patterns, &music, track_sel, SAMPLE_RATE);
+
+                    (error_msg, info_msg) = export_wav(&samples, &export_path(&file_path, &format!("track{}", track_sel), "wav"));
+                }
+
+                // frame list
+
+                let track = &music.tracks[track_sel];
+
+                for (f, frame) in track.frames.iter().enumerate() {
+                    for (c, &p) in frame.iter().enumerate() {
+                        let col = match (f == frame_sel, c == channel) {
+                            (true, true) => YELLOW,
+                            (true, false) => WHITE,
+                            _ => GRAY,
+                        };
+
+                        let label = match p {
+                            0 => "--".to_string(),
+                            _ => format!("{:0>2}", p),
+                        };
+
+                        draw_text(&label, 10.0 + (c as f32) * 40.0, TRACK_TOP + (f as f32) * TRACK_ROW, 20.0, col);
+                    }
+                }
+
+                // the pattern grid of the selected frame, scrolled to keep the cursor in view
+
+                if row_sel < row_scroll {
+                    row_scroll = row_sel;
+                } else if row_sel >= row_scroll + TRACK_VISIBLE {
+                    row_scroll = row_sel + 1 - TRACK_VISIBLE;
+                }
+
+                for r in row_scroll..(row_scroll + TRACK_VISIBLE).min(PATTERN_ROWS) {
+                    let y = TRACK_TOP + ((r - row_scroll) as f32) * TRACK_ROW;
+
+                    let faded = match r < track.rows {
+                        true => WHITE,
+                        false => DARKGRAY,
+                    };
+
+                    draw_text(&format!("{:0>2}", r), TRACK_X - 30.0, y, 20.0, GRAY);
+
+                    for c in 0..CHANNELS {
+                        let x = TRACK_X + (c as f32) * TRACK_CHANNEL_W;
+
+                        let p = track.frames[frame_sel][c] as usize;
+
+                        let row = match p {
+                            0 => Row::default(),
+                            _ => patterns.patterns[p - 1][r],
+                        };
+
+                        let fields = match (p, row.note) {
+                            (0, _) | (_, NOTE_NONE) => ["---".to_string(), "".to_string(), "--".to_string(), "-".to_string(), "-".to_string(), "-".to_string()],
+                            _ => [row.note_label(), "".to_string(), format!("{:0>2}", row.sfx), COMMANDS[row.command as usize].to_string(), format!("{:X}", row.param1), format!("{:X}", row.param2)],
+                        };
+
+                        // the octave is part of the note label, its cursor sits on the digit
+
+                        let offsets = [0.0, 22.0, 40.0, 72.0, 88.0, 100.0];
+
+                        for (k, text) in fields.iter().enumerate() {
+                            draw_text(text, x + offsets[k], y, 20.0, faded);
+                        }
+
+                        if r == row_sel && c == channel {
+                            let k = field_sel % ROW_FIELDS;
+                            let w = match k {
+                                0 => 22.0,
+                                2 => 22.0,
+                                _ => 12.0,
+                            };
+
+                            draw_rectangle_lines(x + offsets[k] - 2.0, y - TRACK_ROW + 2.0, w + 2.0, TRACK_ROW, 1.0, YELLOW);
+                        }
+                    }
+                }
+
+                let pattern = match pattern_id {
+                    0 => "none, use , and . to pick one".to_string(),
+                    p => p.to_string(),
+                };
+
+                draw_text(&format!("Track {}  tempo {} (shift up/down)  speed {} (shift left/right)  rows {} (shift pgup/pgdn)", track_sel, track.tempo, track.speed, track.rows), 10.0, 20.0, 20.0, WHITE);
+                draw_text(&format!("frame {} pattern {}  [ ] track, pgup/pgdn frame, +/- value, zsxdc.. notes, 1 stop, Del clear, P .wav", frame_sel, pattern), 10.0, 40.0, 20.0, YELLOW);
+            },
+            "screen" => {
+                if is_key_pressed(KeyCode::Escape) {
+                    current_state = "main";
+                }
+
+                if is_key_pressed(KeyCode::I) {
+                    importing = true;
+                    import_into = "screen";
+                    current_state = "open";
+                }
+
+                if is_key_pressed(KeyCode::D) {
+                    import_dither = !import_dither;
+                }
+
+                // the map screen the map editor was last looking at
+
+                if is_key_pressed(KeyCode::C) {
+                    screen = Screen::from_map(&map, &tiles, map_x, map_y);
+                    screen_edited = true;
+                    screen_tex = None;
+                }
+
+                if is_key_pressed(KeyCode::E) {
+                    (error_msg, info_msg) = export_png(&screen.render(&palette), &export_path(&file_path, "cover", "png"));
+                }
+
+                let tex = screen_tex.get_or_insert_with(|| {
+                    let img = screen.render(&palette);
+                    let tex = Texture2D::from_rgba8(img.width as u16, img.height as u16, &img.rgba);
+
+                    tex.set_filter(FilterMode::Nearest);
+
+                    tex
+                });
+
+                let (cw, ch) = (SCREEN_W as f32 * COVER_SCALE, SCREEN_H as f32 * COVER_SCALE);
+                let cx = (SCREEN_WIDTH - cw) / 2.0;
+
+                draw_texture_ex(tex, cx, COVER_TOP, WHITE, DrawTextureParams {
+                    dest_size : Some(vec2(cw, ch)),
+                    ..Default::default()
+                });
+
+                draw_rectangle_lines(cx - 2.0, COVER_TOP - 2.0, cw + 4.0, ch + 4.0, 2.0, DARKGRAY);
+
+                draw_text("Cover  I import png, C capture map at the map view, E export png, Esc back", 10.0, 20.0, 20.0, WHITE);
+                draw_text(&format!("D dither on import {}", import_dither), 10.0, 40.0, 20.0, WHITE);
+
+                // old carts keep their cover as a gif, TIC-80 prefers the screen when both exist
+
+                if cart.has(ChunkType::CoverDep) {
+                    draw_text("this cart also has a legacy gif cover, it is kept as it is", 10.0, COVER_TOP + ch + 30.0, 20.0, YELLOW);
+                }
+            },
+            "palette" => {
+                if is_key_pressed(KeyCode::Escape) {
+                    pal_drag = None;
+                    current_state = pal_back;
+                }
+
+                if is_key_pressed(KeyCode::I) {
+                    importing = true;
+                    import_into = "palette";
+                    current_state = "open";
+                }
+
+                // SCN is the screen palette, OVR the overlay one, O switches
+
+                let switched = is_key_pressed(KeyCode::O);
+
+                if switched {
+                    pal_ovr = !pal_ovr;
+                    pal_hex.clear();
+                }
+
+                let pal = match pal_ovr {
+                    true => &mut ovr_palette,
+                    false => &mut palette,
+                };
+
+                if switched {
+                    pal_hsv = rgb_to_hsv(pal[pal_sel]);
+                }
+
+                let before = pal[pal_sel];
+
+                // the 16 swatches, click one to edit it
+
+                for (c, &col) in pal.iter().enumerate() {
+                    let (sx, sy) = (20.0 + ((c % 8) as f32) * (SWATCH + 2.0), 40.0 + ((c / 8) as f32) * (SWATCH + 2.0));
+
+                    if (sx..sx + SWATCH).contains(&mx) && (sy..sy + SWATCH).contains(&my) {
+                        if current_press_l && !last_press_l {
+                            pal_sel = c;
+                            pal_hsv = rgb_to_hsv(col);
+                            pal_hex.clear();
+                        } else if current_press_r && !last_press_r && c != pal_sel {
+                            pal_swap = Some((pal_ovr, pal_sel, c));
+                        }
+                    }
+
+                    draw_rectangle(sx, sy, SWATCH, SWATCH, color_u8!(col.0, col.1, col.2, 255));
+
+                    if c == pal_sel {
+                        draw_rectangle_lines(sx - 2.0, sy - 2.0, SWATCH + 4.0, SWATCH + 4.0, 2.0, WHITE);
+                    }
+                }
+
+                // r, g, b then h, s, v sliders, dragged with the left button
+
+                let col = pal[pal_sel];
+
+                let values = [col.0 as f32 / 255.0, col.1 as f32 / 255.0, col.2 as f32 / 255.0, pal_hsv.0 / 360.0, pal_hsv.1, pal_hsv.2];
+                let labels = ["R", "G", "B", "H", "S", "V"];
+
+                if !current_press_l {
+                    pal_drag = None;
+                }
+
+                for i in 0..values.len() {
+                    let sy = SLIDER_TOP + (i as f32) * 30.0;
+
+                    if current_press_l && !last_press_l && (SLIDER_X - 5.0..SLIDER_X + SLIDER_W + 5.0).contains(&mx) && (sy - 10.0..sy + 10.0).contains(&my) {
+                        pal_drag = Some(i);
+                    }
+                }
+
+                if let Some(i) = pal_drag {
+                    let t = ((mx - SLIDER_X) / SLIDER_W).clamp(0.0, 1.0);
+                    let byte = (t * 255.0).round() as u8;
+
+                    match i {
+                        0 => pal[pal_sel].0 = byte,
+                        1 => pal[pal_sel].1 = byte,
+                        2 => pal[pal_sel].2 = byte,
+                        3 => pal_hsv.0 = t * 359.9,
+                        4 => pal_hsv.1 = t,
+                        _ => pal_hsv.2 = t,
+                    }
+
+                    // keep the other model in step, hsv keeps its hue even on greys
+
+                    match i < 3 {
+                        true => pal_hsv = rgb_to_hsv(pal[pal_sel]),
+                        false => pal[pal_sel] = hsv_to_rgb(pal_hsv),
+                    }
+
+                    pal_hex.clear();
+                }
+
+                for (i, &v) in values.iter().enumerate() {
+                    let sy = SLIDER_TOP + (i as f32) * 30.0;
+
+                    draw_text(labels[i], 15.0, sy + 6.0, 20.0, WHITE);
+                    draw_line(SLIDER_X, sy, SLIDER_X + SLIDER_W, sy, 2.0, DARKGRAY);
+                    draw_rectangle(SLIDER_X + v * SLIDER_W - 3.0, sy - 8.0, 6.0, 16.0, WHITE);
+                }
+
+                // hex entry, applied with Enter
+
+                while let Some(c) = get_char_pressed() {
+                    if c.is_ascii_hexdigit() && pal_hex.len() < 6 {
+                        pal_hex.push(c.to_ascii_lowercase());
+                    }
+                }
+
+                if is_key_pressed(KeyCode::Backspace) {
+                    pal_hex.pop();
+                }
+
+                if is_key_pressed(KeyCode::Enter) {
+                    match parse_hex(&pal_hex) {
+                        Some(c) => {
+                            pal[pal_sel] = c;
+                            pal_hsv = rgb_to_hsv(c);
+                            pal_hex.clear();
+                        },
+                        None => error_msg = format!("{} is not a 6 digit hex colour", pal_hex),
+                    }
+                }
+
+                let col = pal[pal_sel];
+
+                if col != before {
+                    error_msg.clear();
+
+                    match pal_ovr {
+                        true => ovr_edited = true,
+                        false => {
+                            palette_edited = true;
+                            map_tex = None;
+                            screen_tex = None;
+                        }
+                    }
+                }
+
+                draw_rectangle(20.0, 110.0, 8.0 * (SWATCH + 2.0) - 2.0, 60.0, color_u8!(col.0, col.1, col.2, 255));
+
+                let hex = match pal_hex.is_empty() {
+                    true => to_hex(col),
+                    false => format!("{}_", pal_hex),
+                };
+
+                draw_text(&format!("#{}  ({}, {}, {})", hex, col.0, col.1, col.2), 20.0, 200.0, 20.0, WHITE);
+                draw_text(&format!("h {:.0} s {:.2} v {:.2}", pal_hsv.0, pal_hsv.1, pal_hsv.2), 20.0, 220.0, 20.0, WHITE);
+
+                // I imports a palette file, T picks the format X exports to
+
+                if is_key_pressed(KeyCode::T) {
+                    pal_format = (pal_format + 1) % PALETTE_FORMATS.len();
+                }
+
+                let format = PALETTE_FORMATS[pal_format];
+
+                if is_key_pressed(KeyCode::X) {
+                    let path = export_path(&file_path, "palette", format.extension());
+
+                    (error_msg, info_msg) = match write_palette(pal, format).and_then(|b| write_atomic(Path::new(&path), &b, false)) {
+                        Ok(()) => (String::new(), format!("exported {}", path)),
+                        Err(e) => (format!("could not export {} : {}", path, e), String::new()),
+                    };
+                }
+
+                let which = match pal_ovr {
+                    true => "OVR",
+                    false => "SCN",
+                };
+
+                draw_text(&format!("I import .pal/.gpl/.hex/.png, X export as .{} (T format)", format.extension()), 20.0, SLIDER_TOP + 200.0, 20.0, WHITE);
+
+                draw_text(&format!("{} palette, colour {}  type hex + Enter, right click swaps, O switch SCN/OVR, Esc back", which, pal_sel), 10.0, 20.0, 20.0, WHITE);
+
+                // live preview
+
+                let preview = pal.clone();
+
+                black_pal = palette[0];
+                draw_sheet(draw, &preview, black_pal);
+            },
+            "import" => {
+                let Some(img) = &import_src else {
+                    current_state = "main";
+                    next_frame().await;
+                    continue
+                };
+
+                let (iw, ih) = (img.width as usize, img.height as usize);
+
+                draw_text("Click a cell to move the image, D dither, Tab tiles/sprites", 10.0, 20.0, 20.0, WHITE);
+                draw_text("Enter to import, Esc to cancel", 10.0, 40.0, 20.0, WHITE);
+
+                draw_text(&format!("into {} at {}, dither {}", match show_spr { true => "sprites", false => "tiles" }, import_at, import_dither), 10.0, 60.0, 20.0, WHITE);
+
+                if is_key_pressed(KeyCode::Tab) {
+                    show_spr = !show_spr;
+                }
+
+                if is_key_pressed(KeyCode::D) {
+                    import_dither = !import_dither;
+                    import_pix = quantise(img, &palette[..1 << sheet_bpp], import_dither);
+                }
+
+                if let Some(cell) = sheet_cell(mx, my) {
+                    if current_press_l && !last_press_l {
+                        import_at = cell;
+                    }
+                }
+
+                draw_sheet(draw, &palette, black_pal);
+
+                // preview, clipped to the sheet
+
+                let (ax, ay) = (import_at % 16 * 8, import_at / 16 * 8);
+
+                for y in 0..ih.min(128 - ay) {
+                    for x in 0..iw.min(128 - ax) {
+                        let col = palette[import_pix[x + y * iw] as usize];
+
+                        draw_rectangle(OFF_X + ((ax + x) as f32) * PIX_SIZE, OFF_Y + ((ay + y) as f32) * PIX_SIZE, PIX_SIZE, PIX_SIZE, color_u8!(col.0, col.1, col.2, 255));
+                    }
+                }
+
+                draw_rectangle_lines(OFF_X + (ax as f32) * PIX_SIZE, OFF_Y + (ay as f32) * PIX_SIZE, (iw.min(128 - ax) as f32) * PIX_SIZE, (ih.min(128 - ay) as f32) * PIX_SIZE, 2.0, WHITE);
+
+                if is_key_pressed(KeyCode::Enter) {
+                    let (blocks, cols) = slice(&import_pix, iw, ih);
+                    let before = draw.clone();
+
+                    place(draw, &blocks, cols, import_at);
+
+                    history.push(Edit::Sheet { sprites : show_spr, bank : sheet_bank, bpp : sheet_bpp, page : sheet_page, before, after : draw.clone() });
+                }
+
+                if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::Escape) {
+                    importing = false;
+                    import_src = None;
+                    current_state = "main";
+                }
+            },
+            "edit" => {
+
+                // P edits the primary colour, the drawing is kept as it is
+
+                if is_key_pressed(KeyCode::P) {
+                    pal_sel = primary;
+                    pal_ovr = false;
+                    pal_hsv = rgb_to_hsv(palette[pal_sel]);
+                    pal_hex.clear();
+
+                    // chars typed elsewhere are still queued up
+
+                    while get_char_pressed().is_some() {}
+
+                    pal_back = "edit";
+                    current_state = "palette";
+                }
+
+                // the tools below all change to_draw, so each use is one step of the
+                // undo history: fill, the clipboard and moving the pixels around
+
+                draw_text(&format!("F fill ({}), Ctrl+C/X/V copy/cut/paste, arrows move, Ctrl+Z/Y undo/redo, P palette, E export, Esc done", fill_mode), 10.0, 20.0, 20.0, WHITE);
+
+                if is_key_pressed(KeyCode::F) {
+                    fill_mode = !fill_mode;
+                }
+
+                // the clipboard keeps the copied blocks' layout, pasting puts it
+                // in the top left corner of the selection
+
+                let rows = (sel_h / SPR_SIDE_LENGTH) as usize;
+                let cols = to_draw.len() / rows.max(1);
+
+                let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+                if ctrl && (is_key_pressed(KeyCode::C) || is_key_pressed(KeyCode::X)) {
+                    sheet_clip = Some((to_rows(&to_draw, rows), cols));
+
+                    if is_key_pressed(KeyCode::X) {
+                        to_draw = vec![EMPTY_SPR.to_vec(); to_draw.len()];
+                    }
+                }
+
+                if let Some((clip, clip_cols)) = sheet_clip.as_ref().filter(|_| ctrl && is_key_pressed(KeyCode::V)) {
+                    let mut blocks = to_rows(&to_draw, rows);
+
+                    for (i, block) in clip.iter().enumerate() {
+                        let (x, y) = (i % clip_cols, i / clip_cols);
+
+                        if x < cols && y < rows {
+                            blocks[x + y * cols] = block.clone();
+                        }
+                    }
+
+                    to_draw = to_columns(&blocks, rows);
+                }
+
+                // arrows move the whole selection a pixel, wrapping round
+
+                let mut dx = 0;
+                let mut dy = 0;
+
+                if is_key_pressed(KeyCode::Left) { dx -= 1; }
+                if is_key_pressed(KeyCode::Right) { dx += 1; }
+                if is_key_pressed(KeyCode::Up) { dy -= 1; }
+                if is_key_pressed(KeyCode::Down) { dy += 1; }
+
+                if (dx, dy) != (0, 0) && !to_draw.is_empty() {
+                    let pixels = shift(&join(&to_rows(&to_draw, rows), cols), cols * 8, dx, dy);
+
+                    to_draw = to_columns(&slice(&pixels, cols * 8, rows * 8).0, rows);
+                }
+
+                // export just the selection, keeping its layout
+
+                if is_key_pressed(KeyCode::E) {
+                    let rows = (sel_h / SPR_SIDE_LENGTH) as usize;
+                    let cols = (sel_w / SPR_SIDE_LENGTH) as usize;
+
+                    let blocks = to_rows(&to_draw, rows);
+
+                    let zx = (sel_x - OFF_X) / SPR_SIDE_LENGTH;
+                    let zy = (sel_y - OFF_Y) / SPR_SIDE_LENGTH;
+
+                    let what = format!("{}_{}", match show_spr { true => "sprites", false => "tiles" }, (zx + zy * 16.0) as usize);
+
+                    let img = render(&blocks, cols, &palette, export_alpha, export_scale);
+
+                    (error_msg, info_msg) = export_png(&img, &export_path(&file_path, &what, "png"));
+                }
+
+                // only as many colours as the sheet mode has bits for
+
+                let colours = 1usize << sheet_bpp;
+
+                (primary, secondary) = (primary.min(colours - 1), secondary.min(colours - 1));
+
+                draw_rectangle(PALETTE_SIZE - SELECTION_THICK, SCREEN_HEIGHT / 2.0 - 8.0 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE * colours as f32 + SELECTION_THICK * 2.0, WHITE);
+                draw_rectangle(PALETTE_SIZE - SELECTION_THICK, SCREEN_HEIGHT / 2.0 + 9.0 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE + SELECTION_THICK * 2.0, WHITE);
+                draw_rectangle(PALETTE_SIZE * 1.5 - SELECTION_THICK, SCREEN_HEIGHT / 2.0 + 9.5 * PALETTE_SIZE - SELECTION_THICK, PALETTE_SIZE + SELECTION_THICK * 2.0, PALETTE_SIZE + SELECTION_THICK * 2.0, WHITE);
+
+                for (c, &col) in palette.iter().take(colours).enumerate() {
+
+                    let cy = SCREEN_HEIGHT / 2.0 - 8.0 * PALETTE_SIZE + (c as f32) * PALETTE_SIZE;
+
+                    let my_col = color_u8!(col.0, col.1, col.2, 255);
+
+                    if mx < PALETTE_SIZE * 2.0 + SELECTION_THICK
+                    && my >= cy && my < cy + PALETTE_SIZE {
+                        if current_press_l && !last_press_l {
+                            primary = c;
+                        } else if current_press_r && !last_press_r {
+                            secondary = c;
+                        }
+                    }
+
+                    draw_rectangle(PALETTE_SIZE, cy, PALETTE_SIZE, PALETTE_SIZE, my_col);
+                }
+
+                let col = palette[secondary];
+
+                draw_rectangle(PALETTE_SIZE, SCREEN_HEIGHT / 2.0 + 9.0 * PALETTE_SIZE, PALETTE_SIZE, PALETTE_SIZE, color_u8!(col.0, col.1, col.2, 255));
+
+                let col = palette[primary];
+
+                draw_rectangle(PALETTE_SIZE * 1.5, SCREEN_HEIGHT / 2.0 + 9.5 * PALETTE_SIZE, PALETTE_SIZE, PALETTE_SIZE, color_u8!(col.0, col.1, col.2, 255));
+
+                let mult : f32 = if (SCREEN_HEIGHT - 6.0 * PALETTE_SIZE) / sel_h < (SCREEN_WIDTH - 6.0 * PALETTE_SIZE) / sel_w {
+                    (SCREEN_HEIGHT - 6.0 * PALETTE_SIZE) / sel_h
+                } else {
+                    (SCREEN_WIDTH - 6.0 * PALETTE_SIZE) / sel_w
+                };
+
+                let (mut drax, mut dray) : (usize, usize) = (0, 0);
+
+                let mut hover : bool = false;
+
+                for (idx, l) in to_draw.iter().enumerate() {
+                    for (id, i) in l.iter().enumerate() {
+                        let col = palette[*i as usize];
+                        let my_col = color_u8!(col.0, col.1, col.2, 255);
+
+                        let x = (id as f32)%8.0 * PIX_SIZE * mult;
+                        let y = ((id as f32)/8.0).floor() * PIX_SIZE * mult;
+
+                        let ofx = ((idx as f32)/(sel_h / SPR_SIDE_LENGTH)).floor() * SPR_SIDE_LENGTH * mult;
+                        let ofy = (idx as f32)%(sel_h / SPR_SIDE_LENGTH) * SPR_SIDE_LENGTH * mult;
+
+                        let bx = (SCREEN_WIDTH - sel_w * mult) / 2.0;
+                        let by = (SCREEN_HEIGHT - sel_h * mult) / 2.0;
+
+                        draw_rectangle(x + ofx + bx, y + ofy + by, PIX_SIZE * mult, PIX_SIZE * mult, my_col);
+
+                        if mx >= x + ofx + bx
+                        && mx < x + ofx + bx + PIX_SIZE * mult
+                        && my >= y + ofy + by
+                        && my < y + ofy + by + PIX_SIZE * mult {
+                            hover = true;
+
+                            if current_press_l || current_press_r {
+                                drax = idx;
                                 dray = id;
                             }
 
@@ -799,7 +2141,20 @@ async fn main() {
                     }
                 }
 
-                if mx > PALETTE_SIZE * 2.0 + SELECTION_THICK && hover {
+                if mx > PALETTE_SIZE * 2.0 + SELECTION_THICK && hover && fill_mode {
+                    if (current_press_l && !last_press_l) || (current_press_r && !last_press_r) {
+                        let colour = match current_press_l {
+                            true => primary as u8,
+                            false => secondary as u8,
+                        };
+
+                        let mut pixels = join(&to_rows(&to_draw, rows), cols);
+
+                        flood_fill(&mut pixels, cols * 8, drax / rows * 8 + dray % 8, drax % rows * 8 + dray / 8, colour);
+
+                        to_draw = to_columns(&slice(&pixels, cols * 8, rows * 8).0, rows);
+                    }
+                } else if mx > PALETTE_SIZE * 2.0 + SELECTION_THICK && hover {
                     if current_press_l {
                         to_draw[drax][dray] = primary as u8;
                     } else if current_press_r {
@@ -815,10 +2170,9 @@ async fn main() {
 
                     let zid = zx + zy * 16.0;
 
+                    let before = draw.clone();
 
-                    for x in 0..to_draw.len() {
-                        let i = to_draw[x].clone();
-
+                    for (x, i) in to_draw.iter().enumerate() {
                         let ox = (x as f32 / (sel_h / SPR_SIDE_LENGTH)) as i32 as f32;
 
                         for y in 0..i.len() {
@@ -828,17 +2182,21 @@ async fn main() {
                             let zo = ox + oy * 16.0;
 
                             if draw.len() as f32 > zid + zo {
-                                draw[(zid + zo) as usize][y] = to_draw[x][y];
+                                draw[(zid + zo) as usize][y] = i[y];
                             } else {
                                 while (draw.len() as f32) < zid + zo {
                                     draw.push(EMPTY_SPR.to_vec().clone())
                                 }
-                                draw.push(to_draw[x].clone());
+                                draw.push(i.clone());
                                 break
                             }
                         }
                     }
 
+                    // the strokes made here become one sheet edit
+
+                    history.squash(Edit::Sheet { sprites : show_spr, bank : sheet_bank, bpp : sheet_bpp, page : sheet_page, before, after : draw.clone() });
+
                     current_state = "main";
                     (sel_x, sel_y) = (-16.0 * PIX_SIZE, -16.0 * PIX_SIZE);
                     (sel_w, sel_h) = (SPR_SIDE_LENGTH, SPR_SIDE_LENGTH);
@@ -846,23 +2204,261 @@ async fn main() {
             },
             "saving" => {
 
-                let comp_tiles = compress(flatten(tiles.clone()));
-                let comp_sprites = compress(flatten(sprites.clone()));
-                let exp_palette = expand(palette.clone());
+                // only the sheets that changed are written, so untouched chunks keep their bytes
+
+                tile_banks[sheet_bank] = tiles.clone();
+                sprite_banks[sheet_bank] = sprites.clone();
+                map_banks[sheet_bank] = map.clone();
+                flag_banks[sheet_bank] = flags.clone();
+
+                for b in 0..MAX_BANKS {
+                    let (t, s) = (&tile_banks[b as usize], &sprite_banks[b as usize]);
+
+                    if *t != loaded_tiles[b as usize] {
+                        cart.set_tiles_in(b, t);
+                    }
+
+                    if *s != loaded_sprites[b as usize] {
+                        cart.set_sprites_in(b, s);
+                    }
+
+                    if map_edited[b as usize] {
+                        cart.set_map_in(b, &map_banks[b as usize]);
+                    }
+
+                    if flags_edited[b as usize] {
+                        cart.set_flags_in(b, &flag_banks[b as usize]);
+                    }
+                }
+
+                if palette_edited {
+                    cart.set_palette(&palette);
+                }
+
+                if ovr_edited {
+                    cart.set_ovr_palette(&ovr_palette);
+                }
+
+                if waves_edited {
+                    cart.set_waves(&waves);
+                }
+
+                if sounds_edited {
+                    cart.set_sounds(&sounds);
+                }
+
+                if patterns_edited {
+                    cart.set_patterns(&patterns);
+                }
+
+                if screen_edited {
+                    cart.set_screen(&screen);
+                }
+
+                if music_edited {
+                    cart.set_music(&music);
+                }
+
+                if code_edited {
+                    if let Err(e) = cart.set_code(&code_buf.text()) {
+                        error_msg = format!("could not save {} : {}", file_path, e);
+                        println!("{}", error_msg);
+
+                        current_state = "main";
 
-                chunks = replace(chunks, Chunk { name : "Tiles".into(), bank : 0, data : comp_tiles});
-                chunks = replace(chunks, Chunk { name : "Sprites".into(), bank : 0, data : comp_sprites});
-                chunks = replace(chunks, Chunk { name : "Palette".into(), bank : 0, data : exp_palette});
+                        next_frame().await;
+                        continue
+                    }
+                }
+
+                println!("{}", file_path);
+
+                let saved = match KEEP_BACKUP {
+                    true => cart.save_with_backup(&file_path),
+                    false => cart.save(&file_path),
+                };
 
-                construct_tic(file_path.into(), chunks);
+                // stay in the editor if saving failed, nothing is lost yet
 
-                current_state = "open";
+                match saved {
+                    Ok(()) => exit(0x0100),
+                    Err(e) => {
+                        error_msg = format!("could not save {} : {}", file_path, e);
+                        println!("{}", error_msg);
 
-                exit(0x0100);
+                        current_state = "main";
+                    }
+                }
             },
             _ => {},
         }
 
+        // Ctrl+Z / Ctrl+Y, each state only steps through edits it can show:
+        // the selection's strokes while drawing, whole sheets outside of it
+
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+        let allowed = |e: &Edit| match (current_state, e) {
+            ("main", Edit::Selection { .. }) => false,
+            ("main", _) => true,
+            ("edit", Edit::Sheet { .. }) => false,
+            ("edit", _) => true,
+            ("palette", Edit::Palette { .. } | Edit::Swap { .. }) => true,
+            _ => false,
+        };
+
+        let step = match ctrl {
+            true if is_key_pressed(KeyCode::Z) => history.undo(allowed),
+            true if is_key_pressed(KeyCode::Y) => history.redo(allowed),
+            _ => None,
+        };
+
+        let replaying = step.is_some();
+        let mut swap = None;
+
+        match step {
+            Some(Edit::Selection { after, .. }) => {
+                to_draw = after;
+                sel_mark = to_draw.clone();
+            },
+            Some(Edit::Sheet { sprites : spr, bank, bpp, page, after, .. }) => {
+                let sheet = match (spr, bank == sheet_bank) {
+                    (true, true) => &mut sprites,
+                    (false, true) => &mut tiles,
+                    (true, false) => &mut sprite_banks[bank],
+                    (false, false) => &mut tile_banks[bank],
+                };
+
+                match bpp {
+                    4 => *sheet = after,
+                    _ => set_bpp_page(sheet, &after, bpp, page),
+                }
+
+                bpp_key = None;
+                map_tex = None;
+            },
+            Some(Edit::Palette { ovr, after, .. }) => {
+                match ovr {
+                    true => {
+                        ovr_palette = after;
+                        ovr_edited = true;
+                    },
+                    false => {
+                        palette = after;
+                        palette_edited = true;
+                        black_pal = palette[0];
+                        map_tex = None;
+                        screen_tex = None;
+                    }
+                }
+
+                (pal_mark, ovr_mark) = (palette.clone(), ovr_palette.clone());
+                pal_hsv = rgb_to_hsv(match ovr { true => ovr_palette[pal_sel], false => palette[pal_sel] });
+                pal_hex.clear();
+            },
+            Some(Edit::Swap { ovr, a, b, page }) => swap = Some((ovr, a, b, page)),
+            None => {},
+        }
+
+        // below 4bpp a swap only remaps the page on screen, so both colours
+        // have to be ones that page can hold
+
+        if let Some((ovr, a, b)) = pal_swap.take() {
+            let colours = 1usize << sheet_bpp;
+
+            match !ovr && a.max(b) >= colours {
+                true => error_msg = format!("only colours 0-{} can be swapped at {}bpp", colours - 1, sheet_bpp),
+                false => swap = Some((ovr, a, b, (!ovr && sheet_bpp != 4).then_some(SheetPage { sprites : show_spr, bank : sheet_bank, bpp : sheet_bpp, page : sheet_page }))),
+            }
+        }
+
+        // swapping two colours also swaps every pixel using them, so the art
+        // looks the same (only for SCN, which the sheets are drawn with)
+
+        if let Some((ovr, a, b, page)) = swap {
+            match ovr {
+                true => ovr_palette.swap(a, b),
+                false => {
+                    let perm = swap_permutation(a, b);
+
+                    palette = permute_palette(&palette, &perm);
+
+                    match page {
+                        Some(p) => {
+                            let sheet = match (p.sprites, p.bank == sheet_bank) {
+                                (true, true) => &mut sprites,
+                                (false, true) => &mut tiles,
+                                (true, false) => &mut sprite_banks[p.bank],
+                                (false, false) => &mut tile_banks[p.bank],
+                            };
+
+                            remap_bpp_page(sheet, &perm, p.bpp, p.page);
+
+                            if p == (SheetPage { sprites : show_spr, bank : sheet_bank, bpp : sheet_bpp, page : sheet_page }) {
+                                for block in to_draw.iter_mut() {
+                                    remap_pixels(block, &perm);
+                                }
+                            }
+                        },
+                        None => {
+                            // every bank, not just the one on screen
+
+                            let banks = tile_banks.iter_mut().chain(sprite_banks.iter_mut()).flatten();
+
+                            for block in tiles.iter_mut().chain(sprites.iter_mut()).chain(to_draw.iter_mut()).chain(banks) {
+                                remap_pixels(block, &perm);
+                            }
+                        }
+                    }
+
+                    if cart.has(ChunkType::Screen) || screen_edited {
+                        remap_pixels(&mut screen.pixels, &perm);
+                        screen_edited = true;
+                    }
+
+                    (primary, secondary) = (perm[primary] as usize, perm[secondary] as usize);
+                    bpp_key = None;
+
+                    palette_edited = true;
+                    black_pal = palette[0];
+                    map_tex = None;
+                    screen_tex = None;
+                }
+            }
+
+            if !replaying {
+                history.push(Edit::Swap { ovr, a, b, page });
+            }
+
+            ovr_edited |= ovr;
+            pal_sel = b;
+            pal_hsv = rgb_to_hsv(match ovr { true => ovr_palette[b], false => palette[b] });
+            (pal_mark, ovr_mark) = (palette.clone(), ovr_palette.clone());
+            sel_mark = to_draw.clone();
+        }
+
+        // record whatever changed since the marks, once nothing is held down
+
+        if !current_press_l && !current_press_r && pal_drag.is_none() {
+            if current_state == "edit" && to_draw != sel_mark {
+                history.push(Edit::Selection { before : std::mem::replace(&mut sel_mark, to_draw.clone()), after : to_draw.clone() });
+            }
+
+            if palette != pal_mark {
+                history.push(Edit::Palette { ovr : false, before : std::mem::replace(&mut pal_mark, palette.clone()), after : palette.clone() });
+            }
+
+            if ovr_palette != ovr_mark {
+                history.push(Edit::Palette { ovr : true, before : std::mem::replace(&mut ovr_mark, ovr_palette.clone()), after : ovr_palette.clone() });
+            }
+        }
+
+        if !error_msg.is_empty() {
+            draw_text(&error_msg, 10.0, SCREEN_HEIGHT - 10.0, 20.0, RED);
+        } else if !info_msg.is_empty() {
+            draw_text(&info_msg, 10.0, SCREEN_HEIGHT - 10.0, 20.0, GREEN);
+        }
+
         last_press_l = current_press_l;
         last_press_r = current_press_r;
 
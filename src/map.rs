@@ -0,0 +1,119 @@
+use crate::bitmap::Bitmap;
+use crate::cartridge::SPR_PIXELS;
+use crate::sheet::SPR_SIDE;
+
+// the map is 240x136 tile indices, one byte each, made of 8x8 screens of 30x17
+
+pub const MAP_WIDTH : usize = 240;
+pub const MAP_HEIGHT : usize = 136;
+pub const SCREEN_TILES_W : usize = 30;
+pub const SCREEN_TILES_H : usize = 17;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Map {
+    pub cells : Vec<u8>,
+}
+
+impl Default for Map {
+    fn default() -> Map {
+        Map { cells : vec![0; MAP_WIDTH * MAP_HEIGHT] }
+    }
+}
+
+// a rectangle of tile indices picked from the sheet (or the map)
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    pub width : usize,
+    pub height : usize,
+    pub tiles : Vec<u8>,
+}
+
+impl Stamp {
+    pub fn single(tile: u8) -> Stamp {
+        Stamp { width : 1, height : 1, tiles : vec![tile] }
+    }
+
+    // the sheet cells between two corners, in any order
+
+    pub fn from_sheet(a: usize, b: usize) -> Stamp {
+        let (x0, x1) = ((a % 16).min(b % 16), (a % 16).max(b % 16));
+        let (y0, y1) = ((a / 16).min(b / 16), (a / 16).max(b / 16));
+
+        let mut tiles : Vec<u8> = vec![];
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                tiles.push((x + y * 16) as u8);
+            }
+        }
+
+        Stamp { width : x1 - x0 + 1, height : y1 - y0 + 1, tiles }
+    }
+}
+
+impl Map {
+    // the chunk is cut short after the last non zero cell
+
+    pub fn from_chunk(data: &[u8]) -> Map {
+        let mut map = Map::default();
+
+        let len = data.len().min(map.cells.len());
+        map.cells[..len].copy_from_slice(&data[..len]);
+
+        map
+    }
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let used = self.cells.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        self.cells[..used].to_vec()
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[x + y * MAP_WIDTH]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, tile: u8) {
+        if x < MAP_WIDTH && y < MAP_HEIGHT {
+            self.cells[x + y * MAP_WIDTH] = tile;
+        }
+    }
+
+    // paints the stamp with its top left corner at x, y, clipped to the map
+
+    pub fn stamp(&mut self, x: usize, y: usize, stamp: &Stamp) {
+        for sy in 0..stamp.height {
+            for sx in 0..stamp.width {
+                self.set(x + sx, y + sy, stamp.tiles[sx + sy * stamp.width]);
+            }
+        }
+    }
+
+    // one pixel per pixel render of the w x h tiles starting at x, y
+
+    pub fn render(&self, tiles: &[Vec<u8>], palette: &[(u8, u8, u8)], x: usize, y: usize, w: usize, h: usize) -> Bitmap {
+        let w = w.min(MAP_WIDTH.saturating_sub(x));
+        let h = h.min(MAP_HEIGHT.saturating_sub(y));
+
+        let mut img = Bitmap::new((w * SPR_SIDE) as u32, (h * SPR_SIDE) as u32, (0, 0, 0, 255));
+        let empty : Vec<u8> = vec![0; SPR_PIXELS];
+
+        for ty in 0..h {
+            for tx in 0..w {
+                let tile = tiles.get(self.get(x + tx, y + ty) as usize).unwrap_or(&empty);
+
+                for (p, &idx) in tile.iter().enumerate() {
+                    let col = palette[idx as usize];
+
+                    let px = (tx * SPR_SIDE + p % SPR_SIDE) as u32;
+                    let py = (ty * SPR_SIDE + p / SPR_SIDE) as u32;
+
+                    img.set_pixel(px, py, (col.0, col.1, col.2, 255));
+                }
+            }
+        }
+
+        img
+    }
+}
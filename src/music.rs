@@ -0,0 +1,175 @@
+use crate::sfx::NOTE_NAMES;
+
+// the Patterns chunk holds 60 patterns of 64 rows, 3 bytes a row:
+// note | param1 << 4, param2 | command << 4 | sfx bit 5 << 7, sfx bits 0-4 | octave << 5
+//
+// the Music chunk holds 8 tracks of 51 bytes: 16 frames of 3 bytes, each
+// frame being 4 6-bit pattern ids (0 is none, n is pattern n - 1), then
+// tempo - 150, 64 - rows and speed - 6 (all wrapping, so any byte survives)
+
+pub const PATTERN_COUNT : usize = 60;
+pub const PATTERN_ROWS : usize = 64;
+pub const ROW_BYTES : usize = 3;
+pub const TRACK_COUNT : usize = 8;
+pub const FRAME_COUNT : usize = 16;
+pub const CHANNELS : usize = 4;
+pub const TRACK_BYTES : usize = FRAME_COUNT * 3 + 3;
+pub const DEFAULT_TEMPO : i32 = 150;
+pub const DEFAULT_SPEED : i32 = 6;
+
+// note values below this mean "nothing" (0) or "stop the channel" (1)
+
+pub const NOTE_NONE : u8 = 0;
+pub const NOTE_STOP : u8 = 1;
+pub const NOTE_FIRST : u8 = 4;
+
+pub const COMMANDS : [char; 8] = ['-', 'M', 'C', 'J', 'S', 'P', 'V', 'D'];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Row {
+    pub note : u8,
+    pub octave : u8,
+    pub sfx : u8,
+    pub command : u8,
+    pub param1 : u8,
+    pub param2 : u8,
+}
+
+impl Row {
+    pub fn decode(b: &[u8]) -> Row {
+        Row {
+            note : b[0] & 15,
+            param1 : b[0] >> 4,
+            param2 : b[1] & 15,
+            command : (b[1] >> 4) & 7,
+            sfx : (b[1] >> 7) << 5 | (b[2] & 31),
+            octave : b[2] >> 5,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; 3] {
+        [
+            (self.note & 15) | (self.param1 << 4),
+            (self.param2 & 15) | (self.command & 7) << 4 | (self.sfx >> 5) << 7,
+            (self.sfx & 31) | (self.octave << 5),
+        ]
+    }
+
+    // "C-4", "^^^" for a stop and "---" for an empty row
+
+    pub fn note_label(&self) -> String {
+        match self.note {
+            NOTE_NONE => "---".to_string(),
+            NOTE_STOP => "^^^".to_string(),
+            n if n >= NOTE_FIRST => format!("{}{}", NOTE_NAMES[(n - NOTE_FIRST) as usize], self.octave),
+            _ => "???".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patterns {
+    pub patterns : Vec<Vec<Row>>,
+}
+
+impl Default for Patterns {
+    fn default() -> Patterns {
+        Patterns { patterns : vec![vec![Row::default(); PATTERN_ROWS]; PATTERN_COUNT] }
+    }
+}
+
+impl Patterns {
+    pub fn from_chunk(data: &[u8]) -> Patterns {
+        let mut raw = data.to_vec();
+        raw.resize(PATTERN_COUNT * PATTERN_ROWS * ROW_BYTES, 0);
+
+        let rows : Vec<Row> = raw.chunks(ROW_BYTES).map(Row::decode).collect();
+
+        Patterns { patterns : rows.chunks(PATTERN_ROWS).map(|p| p.to_vec()).collect() }
+    }
+
+    // trailing zeros are dropped, like TIC-80 does
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let bytes : Vec<u8> = self.patterns.iter().flatten().flat_map(|r| r.encode()).collect();
+        let used = bytes.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        bytes[..used].to_vec()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Track {
+    pub frames : Vec<[u8; CHANNELS]>,
+    pub tempo : i32,
+    pub speed : i32,
+    pub rows : usize,
+}
+
+impl Default for Track {
+    fn default() -> Track {
+        Track::decode(&[0; TRACK_BYTES])
+    }
+}
+
+impl Track {
+    pub fn decode(b: &[u8]) -> Track {
+        let frames = b[..FRAME_COUNT * 3].chunks(3).map(|f| {
+            let bits = f[0] as u32 | (f[1] as u32) << 8 | (f[2] as u32) << 16;
+
+            [0, 1, 2, 3].map(|c| ((bits >> (c * 6)) & 63) as u8)
+        }).collect();
+
+        let at = FRAME_COUNT * 3;
+
+        Track {
+            frames,
+            tempo : DEFAULT_TEMPO + b[at] as i8 as i32,
+            rows : (PATTERN_ROWS as u8).wrapping_sub(b[at + 1]) as usize,
+            speed : DEFAULT_SPEED + b[at + 2] as i8 as i32,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out : Vec<u8> = vec![];
+
+        for f in &self.frames {
+            let bits = f.iter().enumerate().fold(0u32, |bits, (c, &p)| bits | ((p as u32 & 63) << (c * 6)));
+
+            out.extend_from_slice(&bits.to_le_bytes()[..3]);
+        }
+
+        out.push((self.tempo - DEFAULT_TEMPO) as i8 as u8);
+        out.push((PATTERN_ROWS as u8).wrapping_sub(self.rows as u8));
+        out.push((self.speed - DEFAULT_SPEED) as i8 as u8);
+
+        out
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Music {
+    pub tracks : Vec<Track>,
+}
+
+impl Default for Music {
+    fn default() -> Music {
+        Music { tracks : vec![Track::default(); TRACK_COUNT] }
+    }
+}
+
+impl Music {
+    pub fn from_chunk(data: &[u8]) -> Music {
+        let mut raw = data.to_vec();
+        raw.resize(TRACK_COUNT * TRACK_BYTES, 0);
+
+        Music { tracks : raw.chunks(TRACK_BYTES).map(Track::decode).collect() }
+    }
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let bytes : Vec<u8> = self.tracks.iter().flat_map(|t| t.encode()).collect();
+        let used = bytes.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        bytes[..used].to_vec()
+    }
+}
@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use crate::bitmap::Bitmap;
+use crate::cartridge::CartError;
+
+pub const PALETTE_COLOURS : usize = 16;
+
+// colour conversions for the palette editor, hue is in degrees,
+// saturation and value go from 0 to 1
+
+pub fn rgb_to_hsv(col: (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (col.0 as f32 / 255.0, col.1 as f32 / 255.0, col.2 as f32 / 255.0);
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    let sat = match max == 0.0 {
+        true => 0.0,
+        false => delta / max,
+    };
+
+    (hue, sat, max)
+}
+
+pub fn hsv_to_rgb(hsv: (f32, f32, f32)) -> (u8, u8, u8) {
+    let (h, s, v) = (hsv.0.rem_euclid(360.0), hsv.1.clamp(0.0, 1.0), hsv.2.clamp(0.0, 1.0));
+
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let to_u8 = |f: f32| ((f + m) * 255.0).round() as u8;
+
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+// "1a1c2c", with or without a leading #
+
+pub fn parse_hex(text: &str) -> Option<(u8, u8, u8)> {
+    let text = text.trim().trim_start_matches('#');
+
+    if text.len() != 6 || !text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None
+    }
+
+    let part = |at: usize| u8::from_str_radix(&text[at..at + 2], 16).ok();
+
+    Some((part(0)?, part(2)?, part(4)?))
+}
+
+pub fn to_hex(col: (u8, u8, u8)) -> String {
+    format!("{:02x}{:02x}{:02x}", col.0, col.1, col.2)
+}
+
+// palette files other tools use: JASC .pal (aseprite, paint shop pro),
+// GIMP .gpl, lospec .hex and a png with one pixel per colour
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteFormat {
+    Jasc,
+    Gimp,
+    Hex,
+    Png,
+}
+
+pub const PALETTE_FORMATS : [PaletteFormat; 4] = [PaletteFormat::Jasc, PaletteFormat::Gimp, PaletteFormat::Hex, PaletteFormat::Png];
+
+impl PaletteFormat {
+    pub fn from_path(path: &str) -> Option<PaletteFormat> {
+        let ext = Path::new(path).extension()?.to_string_lossy().to_lowercase();
+
+        PALETTE_FORMATS.into_iter().find(|f| f.extension() == ext)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PaletteFormat::Jasc => "pal",
+            PaletteFormat::Gimp => "gpl",
+            PaletteFormat::Hex => "hex",
+            PaletteFormat::Png => "png",
+        }
+    }
+}
+
+// the first three numbers on a line, if it has them
+
+fn rgb_numbers(line: &str) -> Option<(u8, u8, u8)> {
+    let mut nums = line.split_whitespace().map(|n| n.parse::<u8>());
+
+    match (nums.next(), nums.next(), nums.next()) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+pub fn read_palette(buf: &[u8], format: PaletteFormat) -> Result<Vec<(u8, u8, u8)>, CartError> {
+    if format == PaletteFormat::Png {
+        let img = Bitmap::decode(buf)?;
+
+        return Ok(img.rgba.chunks(4).map(|c| (c[0], c[1], c[2])).collect())
+    }
+
+    let text = String::from_utf8_lossy(buf);
+    let mut colours : Vec<(u8, u8, u8)> = vec![];
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        // headers: JASC-PAL, version and count / GIMP Palette, Name:, Columns:, # comments
+
+        let header = match format {
+            PaletteFormat::Jasc => n < 3,
+            PaletteFormat::Gimp => n == 0 || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:"),
+            _ => line.starts_with(';'),
+        };
+
+        if header || line.is_empty() {
+            continue
+        }
+
+        let col = match format {
+            PaletteFormat::Hex => parse_hex(line),
+            _ => rgb_numbers(line),
+        };
+
+        match col {
+            Some(c) => colours.push(c),
+            None => return Err(CartError::BadPalette { line : n + 1, text : line.to_string() }),
+        }
+    }
+
+    Ok(colours)
+}
+
+pub fn write_palette(colours: &[(u8, u8, u8)], format: PaletteFormat) -> Result<Vec<u8>, CartError> {
+    let text = match format {
+        PaletteFormat::Jasc => {
+            let lines : Vec<String> = colours.iter().map(|c| format!("{} {} {}", c.0, c.1, c.2)).collect();
+
+            format!("JASC-PAL\r\n0100\r\n{}\r\n{}\r\n", colours.len(), lines.join("\r\n"))
+        },
+        PaletteFormat::Gimp => {
+            let lines : Vec<String> = colours.iter().map(|c| format!("{:>3} {:>3} {:>3}\t{}", c.0, c.1, c.2, to_hex(*c))).collect();
+
+            format!("GIMP Palette\nName: TIC-80\nColumns: {}\n#\n{}\n", colours.len(), lines.join("\n"))
+        },
+        PaletteFormat::Hex => colours.iter().map(|&c| to_hex(c) + "\n").collect(),
+        PaletteFormat::Png => {
+            let mut img = Bitmap::new(colours.len().max(1) as u32, 1, (0, 0, 0, 255));
+
+            for (x, c) in colours.iter().enumerate() {
+                img.set_pixel(x as u32, 0, (c.0, c.1, c.2, 255));
+            }
+
+            return img.encode_png()
+        },
+    };
+
+    Ok(text.into_bytes())
+}
+
+// the first 16 colours, padded with black, and how many had to be left out
+
+pub fn fit_palette(colours: &[(u8, u8, u8)]) -> (Vec<(u8, u8, u8)>, usize) {
+    let mut fitted : Vec<(u8, u8, u8)> = colours.iter().take(PALETTE_COLOURS).copied().collect();
+    fitted.resize(PALETTE_COLOURS, (0, 0, 0));
+
+    (fitted, colours.len().saturating_sub(PALETTE_COLOURS))
+}
+
+// a permutation maps every old colour index to its new one
+
+pub fn swap_permutation(a: usize, b: usize) -> Vec<u8> {
+    let mut perm : Vec<u8> = (0..PALETTE_COLOURS as u8).collect();
+    perm.swap(a, b);
+
+    perm
+}
+
+pub fn is_permutation(perm: &[u8]) -> bool {
+    let mut seen = [false; PALETTE_COLOURS];
+
+    perm.len() == PALETTE_COLOURS && perm.iter().all(|&p| (p as usize) < PALETTE_COLOURS && !std::mem::replace(&mut seen[p as usize], true))
+}
+
+pub fn permute_palette(palette: &[(u8, u8, u8)], perm: &[u8]) -> Vec<(u8, u8, u8)> {
+    let mut out = palette.to_vec();
+
+    for (old, &new) in perm.iter().enumerate() {
+        out[new as usize] = palette[old];
+    }
+
+    out
+}
+
+// moves pixels along with their colours, so the picture looks the same
+// through the permuted palette
+
+pub fn remap_pixels(pixels: &mut [u8], perm: &[u8]) {
+    for p in pixels.iter_mut() {
+        *p = perm[*p as usize];
+    }
+}
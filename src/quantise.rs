@@ -0,0 +1,77 @@
+use crate::bitmap::Bitmap;
+
+// pixels with less alpha than this become colour 0
+pub const ALPHA_CUTOFF : u8 = 128;
+
+fn distance(a: (f32, f32, f32), b: (u8, u8, u8)) -> f32 {
+    let dr = a.0 - b.0 as f32;
+    let dg = a.1 - b.1 as f32;
+    let db = a.2 - b.2 as f32;
+
+    dr * dr + dg * dg + db * db
+}
+
+pub fn nearest(col: (f32, f32, f32), palette: &[(u8, u8, u8)]) -> u8 {
+    let mut best = 0;
+
+    for (i, &p) in palette.iter().enumerate() {
+        if distance(col, p) < distance(col, palette[best]) {
+            best = i;
+        }
+    }
+
+    best as u8
+}
+
+// maps every pixel of the bitmap to a palette index, row by row,
+// optionally spreading the error with floyd-steinberg dithering
+
+pub fn quantise(img: &Bitmap, palette: &[(u8, u8, u8)], dither: bool) -> Vec<u8> {
+    let (w, h) = (img.width as usize, img.height as usize);
+
+    let mut work : Vec<(f32, f32, f32)> = img.rgba.chunks(4).map(|c| (c[0] as f32, c[1] as f32, c[2] as f32)).collect();
+    let mut out : Vec<u8> = vec![0; w * h];
+
+    for y in 0..h {
+        for x in 0..w {
+            let at = x + y * w;
+
+            if img.rgba[at * 4 + 3] < ALPHA_CUTOFF {
+                continue
+            }
+
+            let col = work[at];
+            let idx = nearest(col, palette);
+
+            out[at] = idx;
+
+            if !dither {
+                continue
+            }
+
+            let got = palette[idx as usize];
+            let err = (col.0 - got.0 as f32, col.1 - got.1 as f32, col.2 - got.2 as f32);
+
+            let mut spread = |dx: isize, dy: usize, part: f32| {
+                let nx = x as isize + dx;
+
+                if nx < 0 || nx >= w as isize || y + dy >= h {
+                    return
+                }
+
+                let n = &mut work[nx as usize + (y + dy) * w];
+
+                n.0 += err.0 * part;
+                n.1 += err.1 * part;
+                n.2 += err.2 * part;
+            };
+
+            spread(1, 0, 7.0 / 16.0);
+            spread(-1, 1, 3.0 / 16.0);
+            spread(0, 1, 5.0 / 16.0);
+            spread(1, 1, 1.0 / 16.0);
+        }
+    }
+
+    out
+}
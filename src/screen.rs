@@ -0,0 +1,85 @@
+use crate::bitmap::Bitmap;
+use crate::map::{MAP_HEIGHT, MAP_WIDTH, Map};
+use crate::quantise::quantise;
+use crate::sheet::SPR_SIDE;
+
+// the Screen chunk is a 240x136 snapshot of the 4bpp vram, two pixels a
+// byte low nibble first; TIC-80 shows it as the cart's cover
+
+pub const SCREEN_W : usize = 240;
+pub const SCREEN_H : usize = 136;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+    pub pixels : Vec<u8>,
+}
+
+impl Default for Screen {
+    fn default() -> Screen {
+        Screen { pixels : vec![0; SCREEN_W * SCREEN_H] }
+    }
+}
+
+impl Screen {
+    pub fn from_chunk(data: &[u8]) -> Screen {
+        let mut screen = Screen::default();
+
+        for (i, b) in data.iter().take(SCREEN_W * SCREEN_H / 2).enumerate() {
+            screen.pixels[i * 2] = b & 15;
+            screen.pixels[i * 2 + 1] = b >> 4;
+        }
+
+        screen
+    }
+
+    // trailing zeros are dropped, like TIC-80 does
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let bytes : Vec<u8> = self.pixels.chunks(2).map(|p| (p[0] & 15) | (p[1] << 4)).collect();
+        let used = bytes.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        bytes[..used].to_vec()
+    }
+
+    // any picture, stretched to fit and quantised to the palette
+
+    pub fn from_bitmap(img: &Bitmap, palette: &[(u8, u8, u8)], dither: bool) -> Screen {
+        let fit = img.resized(SCREEN_W as u32, SCREEN_H as u32);
+
+        Screen { pixels : quantise(&fit, palette, dither) }
+    }
+
+    // the 30x17 tiles of the map starting at x, y, as the game would draw them
+
+    pub fn from_map(map: &Map, tiles: &[Vec<u8>], x: usize, y: usize) -> Screen {
+        let mut screen = Screen::default();
+
+        for py in 0..SCREEN_H {
+            for px in 0..SCREEN_W {
+                let (tx, ty) = (x + px / SPR_SIDE, y + py / SPR_SIDE);
+
+                if tx >= MAP_WIDTH || ty >= MAP_HEIGHT {
+                    continue
+                }
+
+                if let Some(tile) = tiles.get(map.get(tx, ty) as usize) {
+                    screen.pixels[px + py * SCREEN_W] = tile[px % SPR_SIDE + py % SPR_SIDE * SPR_SIDE];
+                }
+            }
+        }
+
+        screen
+    }
+
+    pub fn render(&self, palette: &[(u8, u8, u8)]) -> Bitmap {
+        let mut img = Bitmap::new(SCREEN_W as u32, SCREEN_H as u32, (0, 0, 0, 255));
+
+        for (i, &idx) in self.pixels.iter().enumerate() {
+            let col = palette[idx as usize];
+
+            img.set_pixel((i % SCREEN_W) as u32, (i / SCREEN_W) as u32, (col.0, col.1, col.2, 255));
+        }
+
+        img
+    }
+}
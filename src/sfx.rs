@@ -0,0 +1,198 @@
+// the 64 sound effects of the Samples chunk, 66 bytes each:
+// 30 ticks of 2 bytes (volume | wave << 4, arpeggio | pitch << 4),
+// octave | pitch16x << 3 | speed << 4 | reverse << 7,
+// note | stereo left << 4 | stereo right << 5 | 2 unused bits,
+// then a start | size << 4 loop byte for each envelope
+
+pub const SFX_COUNT : usize = 64;
+pub const SFX_TICKS : usize = 30;
+pub const SFX_BYTES : usize = 66;
+pub const NOTE_NAMES : [&str; 12] = ["C-", "C#", "D-", "D#", "E-", "F-", "F#", "G-", "G#", "A-", "A#", "B-"];
+pub const ENVELOPES : [Envelope; 4] = [Envelope::Wave, Envelope::Volume, Envelope::Arpeggio, Envelope::Pitch];
+
+// volume is stored the TIC-80 way, 0 is the loudest and 15 is silent
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tick {
+    pub volume : u8,
+    pub wave : u8,
+    pub arpeggio : u8,
+    pub pitch : i8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Envelope {
+    Wave,
+    Volume,
+    Arpeggio,
+    Pitch,
+}
+
+impl Envelope {
+    // position of its loop in the loop bytes
+
+    pub fn index(self) -> usize {
+        match self {
+            Envelope::Wave => 0,
+            Envelope::Volume => 1,
+            Envelope::Arpeggio => 2,
+            Envelope::Pitch => 3,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Envelope::Wave => "wave",
+            Envelope::Volume => "volume",
+            Envelope::Arpeggio => "arpeggio",
+            Envelope::Pitch => "pitch",
+        }
+    }
+
+    pub fn range(self) -> (i8, i8) {
+        match self {
+            Envelope::Pitch => (-8, 7),
+            _ => (0, 15),
+        }
+    }
+}
+
+// a size of 0 means no loop
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Loop {
+    pub start : u8,
+    pub size : u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sfx {
+    pub ticks : Vec<Tick>,
+    pub octave : u8,
+    pub pitch16x : bool,
+    pub speed : i8,
+    pub reverse : bool,
+    pub note : u8,
+    pub stereo_left : bool,
+    pub stereo_right : bool,
+    pub spare : u8,
+    pub loops : [Loop; 4],
+}
+
+impl Default for Sfx {
+    fn default() -> Sfx {
+        Sfx::decode(&[0; SFX_BYTES])
+    }
+}
+
+// sign extends the low `bits` bits
+
+fn signed(v: u8, bits: u32) -> i8 {
+    ((v << (8 - bits)) as i8) >> (8 - bits)
+}
+
+impl Sfx {
+    pub fn decode(data: &[u8]) -> Sfx {
+        let ticks = data[..SFX_TICKS * 2].chunks(2).map(|t| Tick {
+            volume : t[0] & 15,
+            wave : t[0] >> 4,
+            arpeggio : t[1] & 15,
+            pitch : signed(t[1] >> 4, 4),
+        }).collect();
+
+        let (a, b) = (data[SFX_TICKS * 2], data[SFX_TICKS * 2 + 1]);
+
+        let mut loops = [Loop::default(); 4];
+
+        for (i, l) in loops.iter_mut().enumerate() {
+            let v = data[SFX_TICKS * 2 + 2 + i];
+            *l = Loop { start : v & 15, size : v >> 4 };
+        }
+
+        Sfx {
+            ticks,
+            octave : a & 7,
+            pitch16x : a & 8 != 0,
+            speed : signed((a >> 4) & 7, 3),
+            reverse : a & 128 != 0,
+            note : b & 15,
+            stereo_left : b & 16 != 0,
+            stereo_right : b & 32 != 0,
+            spare : b >> 6,
+            loops,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out : Vec<u8> = vec![];
+
+        for t in &self.ticks {
+            out.push((t.volume & 15) | (t.wave << 4));
+            out.push((t.arpeggio & 15) | ((t.pitch as u8) << 4));
+        }
+
+        out.push((self.octave & 7) | (self.pitch16x as u8) << 3 | ((self.speed as u8) & 7) << 4 | (self.reverse as u8) << 7);
+        out.push((self.note & 15) | (self.stereo_left as u8) << 4 | (self.stereo_right as u8) << 5 | self.spare << 6);
+
+        for l in &self.loops {
+            out.push((l.start & 15) | (l.size << 4));
+        }
+
+        out
+    }
+
+    pub fn get(&self, env: Envelope, tick: usize) -> i8 {
+        let t = &self.ticks[tick];
+
+        match env {
+            Envelope::Wave => t.wave as i8,
+            Envelope::Volume => t.volume as i8,
+            Envelope::Arpeggio => t.arpeggio as i8,
+            Envelope::Pitch => t.pitch,
+        }
+    }
+
+    // values are clamped to what the envelope can hold
+
+    pub fn set(&mut self, env: Envelope, tick: usize, value: i8) {
+        let (lo, hi) = env.range();
+        let value = value.clamp(lo, hi);
+        let t = &mut self.ticks[tick];
+
+        match env {
+            Envelope::Wave => t.wave = value as u8,
+            Envelope::Volume => t.volume = value as u8,
+            Envelope::Arpeggio => t.arpeggio = value as u8,
+            Envelope::Pitch => t.pitch = value,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sounds {
+    pub sfx : Vec<Sfx>,
+}
+
+impl Default for Sounds {
+    fn default() -> Sounds {
+        Sounds { sfx : vec![Sfx::default(); SFX_COUNT] }
+    }
+}
+
+impl Sounds {
+    pub fn from_chunk(data: &[u8]) -> Sounds {
+        let mut raw = data.to_vec();
+        raw.resize(SFX_COUNT * SFX_BYTES, 0);
+
+        Sounds { sfx : raw.chunks(SFX_BYTES).map(Sfx::decode).collect() }
+    }
+
+    // trailing zeros are dropped, like TIC-80 does
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let bytes : Vec<u8> = self.sfx.iter().flat_map(|s| s.encode()).collect();
+        let used = bytes.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        bytes[..used].to_vec()
+    }
+}
@@ -0,0 +1,259 @@
+use crate::bitmap::Bitmap;
+use crate::cartridge::SPR_PIXELS;
+use crate::palette::remap_pixels;
+
+// a sheet is a 16x16 grid of 8x8 sprites, each stored as 64 palette indices
+
+pub const SPR_SIDE : usize = 8;
+pub const SHEET_COLS : usize = 16;
+pub const SHEET_CELLS : usize = SHEET_COLS * SHEET_COLS;
+
+// cuts a width x height index image into 8x8 blocks, row of blocks by row
+// of blocks, padding the right and bottom edges with colour 0
+// returns the blocks and how many of them make a row
+
+pub fn slice(pixels: &[u8], width: usize, height: usize) -> (Vec<Vec<u8>>, usize) {
+    let cols = width.div_ceil(SPR_SIDE);
+    let rows = height.div_ceil(SPR_SIDE);
+
+    let mut blocks : Vec<Vec<u8>> = vec![];
+
+    for by in 0..rows {
+        for bx in 0..cols {
+            let mut block : Vec<u8> = vec![0; SPR_PIXELS];
+
+            for y in 0..SPR_SIDE {
+                for x in 0..SPR_SIDE {
+                    let (px, py) = (bx * SPR_SIDE + x, by * SPR_SIDE + y);
+
+                    if px < width && py < height {
+                        block[x + y * SPR_SIDE] = pixels[px + py * width];
+                    }
+                }
+            }
+
+            blocks.push(block);
+        }
+    }
+
+    (blocks, cols)
+}
+
+// writes blocks into the sheet keeping their layout, with the top left
+// block at sheet index `at`; whatever falls off the sheet is dropped
+
+pub fn place(sheet: &mut Vec<Vec<u8>>, blocks: &[Vec<u8>], cols: usize, at: usize) {
+    let (ax, ay) = (at % SHEET_COLS, at / SHEET_COLS);
+
+    for (i, block) in blocks.iter().enumerate() {
+        let (x, y) = (ax + i % cols, ay + i / cols);
+
+        if x >= SHEET_COLS || y >= SHEET_COLS {
+            continue
+        }
+
+        let id = x + y * SHEET_COLS;
+
+        while sheet.len() <= id {
+            sheet.push(vec![0; SPR_PIXELS]);
+        }
+
+        sheet[id] = block.clone();
+    }
+}
+
+// draws blocks (row of blocks by row of blocks, `cols` wide) through the
+// palette, each pixel blown up to scale x scale; colour 0 can be left
+// see-through for sprites
+
+pub fn render(blocks: &[Vec<u8>], cols: usize, palette: &[(u8, u8, u8)], transparent: bool, scale: u32) -> Bitmap {
+    let cols = cols.max(1);
+    let rows = blocks.len().div_ceil(cols).max(1);
+    let scale = scale.max(1);
+
+    let width = (cols * SPR_SIDE) as u32 * scale;
+    let height = (rows * SPR_SIDE) as u32 * scale;
+
+    let mut img = Bitmap::new(width, height, (0, 0, 0, 0));
+
+    for (i, block) in blocks.iter().enumerate() {
+        let (bx, by) = (i % cols * SPR_SIDE, i / cols * SPR_SIDE);
+
+        for (p, &idx) in block.iter().enumerate() {
+            let col = palette[idx as usize];
+
+            let alpha = match transparent && idx == 0 {
+                true => 0,
+                false => 255,
+            };
+
+            let (x, y) = ((bx + p % SPR_SIDE) as u32 * scale, (by + p / SPR_SIDE) as u32 * scale);
+
+            for sy in 0..scale {
+                for sx in 0..scale {
+                    img.set_pixel(x + sx, y + sy, (col.0, col.1, col.2, alpha));
+                }
+            }
+        }
+    }
+
+    img
+}
+
+// the whole 16x16 sheet, with missing sprites left blank
+
+pub fn render_sheet(sheet: &[Vec<u8>], palette: &[(u8, u8, u8)], transparent: bool, scale: u32) -> Bitmap {
+    let mut full = sheet.to_vec();
+    full.resize(SHEET_CELLS, vec![0; SPR_PIXELS]);
+
+    render(&full, SHEET_COLS, palette, transparent, scale)
+}
+
+// TIC-80 can read the same sheet memory at 4, 2 or 1 bits per pixel, the
+// lowest bits holding the leftmost pixel; fewer bits give more sprites with
+// fewer colours, shown 256 at a time as pages
+
+pub const SHEET_BYTES : usize = SHEET_CELLS * SPR_PIXELS / 2;
+pub const BPP_MODES : [u8; 3] = [4, 2, 1];
+
+pub fn bpp_pages(bpp: u8) -> usize {
+    4 / bpp as usize
+}
+
+// every block the bytes hold at `bpp`, short data is padded to a full sheet
+
+pub fn unpack_bpp(data: &[u8], bpp: u8) -> Vec<Vec<u8>> {
+    let per_byte = 8 / bpp as usize;
+    let mask = (1u8 << bpp) - 1;
+
+    let mut bytes = data.to_vec();
+    bytes.resize(bytes.len().max(SHEET_BYTES), 0);
+
+    bytes.chunks(SPR_PIXELS / per_byte).map(|c| {
+        let mut block : Vec<u8> = vec![0; SPR_PIXELS];
+
+        for (p, i) in block.iter_mut().enumerate() {
+            *i = (c.get(p / per_byte).copied().unwrap_or(0) >> (p % per_byte * bpp as usize)) & mask;
+        }
+
+        block
+    }).collect()
+}
+
+// the reverse, pixels too big for `bpp` lose their high bits
+
+pub fn pack_bpp(blocks: &[Vec<u8>], bpp: u8) -> Vec<u8> {
+    let per_byte = 8 / bpp as usize;
+    let mask = (1u8 << bpp) - 1;
+
+    blocks.iter().flat_map(|b| b.chunks(per_byte).map(|c| {
+        c.iter().enumerate().fold(0u8, |acc, (k, &i)| acc | (i & mask) << (k * bpp as usize))
+    }).collect::<Vec<u8>>()).collect()
+}
+
+// page `page` of a 4bpp sheet read back at `bpp`, always SHEET_CELLS blocks
+
+pub fn bpp_page(sheet: &[Vec<u8>], bpp: u8, page: usize) -> Vec<Vec<u8>> {
+    unpack_bpp(&pack_bpp(sheet, 4), bpp).into_iter().skip(page * SHEET_CELLS).take(SHEET_CELLS).collect()
+}
+
+// writes an edited page back into the 4bpp sheet, which only grows as far
+// as the page actually reaches
+
+pub fn set_bpp_page(sheet: &mut Vec<Vec<u8>>, blocks: &[Vec<u8>], bpp: u8, page: usize) {
+    let mut all = unpack_bpp(&pack_bpp(sheet, 4), bpp);
+
+    for (i, block) in blocks.iter().take(SHEET_CELLS).enumerate() {
+        if let Some(at) = all.get_mut(page * SHEET_CELLS + i) {
+            *at = block.clone();
+        }
+    }
+
+    let mut full = unpack_bpp(&pack_bpp(&all, bpp), 4);
+    let used = full.iter().rposition(|b| b.iter().any(|&i| i != 0)).map_or(0, |i| i + 1);
+
+    full.truncate(used.max(sheet.len()));
+
+    *sheet = full;
+}
+
+// swaps colours in one page only; the other pages share its bytes, so
+// remapping the 4bpp pixels would scramble them
+
+pub fn remap_bpp_page(sheet: &mut Vec<Vec<u8>>, perm: &[u8], bpp: u8, page: usize) {
+    let mut blocks = bpp_page(sheet, bpp, page);
+
+    for block in blocks.iter_mut() {
+        remap_pixels(block, perm);
+    }
+
+    set_bpp_page(sheet, &blocks, bpp, page);
+}
+
+// the other way round from slice, blocks back into one index image
+// `cols` blocks wide
+
+pub fn join(blocks: &[Vec<u8>], cols: usize) -> Vec<u8> {
+    let cols = cols.max(1);
+    let rows = blocks.len().div_ceil(cols);
+    let width = cols * SPR_SIDE;
+
+    let mut pixels : Vec<u8> = vec![0; width * rows * SPR_SIDE];
+
+    for (i, block) in blocks.iter().enumerate() {
+        let (bx, by) = (i % cols * SPR_SIDE, i / cols * SPR_SIDE);
+
+        for (p, &idx) in block.iter().enumerate() {
+            pixels[bx + p % SPR_SIDE + (by + p / SPR_SIDE) * width] = idx;
+        }
+    }
+
+    pixels
+}
+
+// paints the area of same coloured pixels around (x, y), without diagonals
+
+pub fn flood_fill(pixels: &mut [u8], width: usize, x: usize, y: usize, colour: u8) {
+    let height = pixels.len() / width.max(1);
+
+    if x >= width || y >= height || pixels[x + y * width] == colour {
+        return
+    }
+
+    let from = pixels[x + y * width];
+    let mut todo : Vec<(usize, usize)> = vec![(x, y)];
+
+    while let Some((x, y)) = todo.pop() {
+        if pixels[x + y * width] != from {
+            continue
+        }
+
+        pixels[x + y * width] = colour;
+
+        if x > 0 { todo.push((x - 1, y)); }
+        if y > 0 { todo.push((x, y - 1)); }
+        if x + 1 < width { todo.push((x + 1, y)); }
+        if y + 1 < height { todo.push((x, y + 1)); }
+    }
+}
+
+// moves every pixel by (dx, dy), whatever falls off one edge comes back
+// in on the other
+
+pub fn shift(pixels: &[u8], width: usize, dx: i32, dy: i32) -> Vec<u8> {
+    let width = width.max(1);
+    let height = pixels.len() / width;
+
+    let mut out : Vec<u8> = vec![0; pixels.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let nx = (x as i32 + dx).rem_euclid(width as i32) as usize;
+            let ny = (y as i32 + dy).rem_euclid(height.max(1) as i32) as usize;
+
+            out[nx + ny * width] = pixels[x + y * width];
+        }
+    }
+
+    out
+}
@@ -0,0 +1,222 @@
+use crate::music::{CHANNELS, NOTE_FIRST, NOTE_STOP, PATTERN_ROWS, Music, Patterns};
+use crate::sfx::{Envelope, Loop, SFX_TICKS, Sfx, Sounds};
+use crate::wave::{WAVE_MAX, WAVE_SAMPLES, Waves};
+
+// a software take on TIC-80's sound: 4 channels, each playing a sfx whose
+// envelopes pick a 32 sample waveform, volume, arpeggio and pitch every
+// 1/60th of a second
+//
+// music commands other than M (channel volume) aren't played
+
+pub const TICKS_PER_SECOND : u32 = 60;
+
+// share of full scale each channel gets, so 4 loud channels don't clip
+
+const CHANNEL_GAIN : f32 = 0.25;
+
+// tick rows per minute at the default tempo and speed, see TIC-80's tick2row
+
+const NOTES_PER_MINUTE : i64 = 900;
+
+// semitones counted from C-0, A-4 is 440 hz
+
+pub fn note_freq(semitone: i32) -> f32 {
+    440.0 * 2f32.powf((semitone - 57) as f32 / 12.0)
+}
+
+// envelope position after `ticks` ticks at the sfx speed
+// (above 0 it's sped up, below 0 slowed down)
+
+pub fn sfx_pos(speed: i8, ticks: usize) -> usize {
+    match speed > 0 {
+        true => ticks * (1 + speed as usize),
+        false => ticks / (1 + speed.unsigned_abs() as usize),
+    }
+}
+
+// without a loop the last tick is held, like TIC-80 does
+
+pub fn loop_pos(l: Loop, pos: usize) -> usize {
+    let (start, size) = (l.start as usize, l.size as usize);
+
+    match size > 0 && pos >= start + size {
+        true => start + (pos - start) % size,
+        false => pos.min(SFX_TICKS - 1),
+    }
+}
+
+// how many ticks one pass over the envelopes takes
+
+pub fn sfx_length(sfx: &Sfx) -> usize {
+    (0..).find(|&t| sfx_pos(sfx.speed, t) >= SFX_TICKS).unwrap_or(SFX_TICKS)
+}
+
+#[derive(Clone, Debug)]
+struct Voice {
+    sfx : usize,
+    semitone : i32,
+    ticks : usize,
+    phase : f32,
+}
+
+pub struct Synth<'a> {
+    waves : &'a Waves,
+    sounds : &'a Sounds,
+    voices : Vec<Option<Voice>>,
+    volumes : Vec<f32>,
+    rate : u32,
+    carry : f32,
+}
+
+impl<'a> Synth<'a> {
+    pub fn new(waves: &'a Waves, sounds: &'a Sounds, rate: u32) -> Synth<'a> {
+        Synth { waves, sounds, voices : vec![None; CHANNELS], volumes : vec![1.0; CHANNELS], rate, carry : 0.0 }
+    }
+
+    // `semitone` defaults to the note stored in the sfx
+
+    pub fn play(&mut self, channel: usize, sfx: usize, semitone: Option<i32>) {
+        let s = &self.sounds.sfx[sfx];
+        let semitone = semitone.unwrap_or(s.octave as i32 * 12 + s.note as i32);
+
+        self.voices[channel] = Some(Voice { sfx, semitone, ticks : 0, phase : 0.0 });
+    }
+
+    pub fn stop(&mut self, channel: usize) {
+        self.voices[channel] = None;
+    }
+
+    pub fn set_volume(&mut self, channel: usize, volume: f32) {
+        self.volumes[channel] = volume.clamp(0.0, 1.0);
+    }
+
+    // renders one tick worth of samples and moves every voice on a tick
+
+    pub fn tick(&mut self, out: &mut Vec<i16>) {
+        let exact = self.rate as f32 / TICKS_PER_SECOND as f32 + self.carry;
+        let count = exact as usize;
+
+        self.carry = exact - count as f32;
+
+        let mut mix : Vec<f32> = vec![0.0; count];
+
+        for (voice, &master) in self.voices.iter_mut().zip(&self.volumes) {
+            let Some(v) = voice else {
+                continue
+            };
+
+            let sfx = &self.sounds.sfx[v.sfx];
+            let pos = sfx_pos(sfx.speed, v.ticks);
+
+            let at = |env: Envelope| sfx.get(env, loop_pos(sfx.loops[env.index()], pos)) as i32;
+
+            let wave = &self.waves.waves[at(Envelope::Wave) as usize];
+            let volume = (WAVE_MAX as i32 - at(Envelope::Volume)) as f32 / WAVE_MAX as f32 * master;
+
+            let pitch = match sfx.pitch16x {
+                true => at(Envelope::Pitch) * 16,
+                false => at(Envelope::Pitch),
+            };
+
+            let freq = (note_freq(v.semitone + at(Envelope::Arpeggio)) + pitch as f32).max(0.0);
+            let step = freq / self.rate as f32;
+
+            for m in mix.iter_mut() {
+                let mut idx = (v.phase * WAVE_SAMPLES as f32) as usize % WAVE_SAMPLES;
+
+                if sfx.reverse {
+                    idx = WAVE_SAMPLES - 1 - idx;
+                }
+
+                let level = wave[idx] as f32 / WAVE_MAX as f32 * 2.0 - 1.0;
+
+                *m += level * volume * CHANNEL_GAIN;
+                v.phase = (v.phase + step).fract();
+            }
+
+            v.ticks += 1;
+        }
+
+        out.extend(mix.iter().map(|m| (m.clamp(-1.0, 1.0) * i16::MAX as f32) as i16));
+    }
+}
+
+// one sfx on its own note, for `seconds` or a single pass when that's None
+
+pub fn render_sfx(waves: &Waves, sounds: &Sounds, id: usize, seconds: Option<f32>, rate: u32) -> Vec<i16> {
+    let ticks = match seconds {
+        Some(s) => (s * TICKS_PER_SECOND as f32) as usize,
+        None => sfx_length(&sounds.sfx[id]),
+    };
+
+    let mut synth = Synth::new(waves, sounds, rate);
+    let mut out : Vec<i16> = vec![];
+
+    synth.play(0, id, None);
+
+    for _ in 0..ticks {
+        synth.tick(&mut out);
+    }
+
+    out
+}
+
+// plays the track's frames up to the last one that uses a pattern
+
+pub fn render_track(waves: &Waves, sounds: &Sounds, patterns: &Patterns, music: &Music, track: usize, rate: u32) -> Vec<i16> {
+    let t = &music.tracks[track];
+    let frames = t.frames.iter().rposition(|f| f.iter().any(|&p| p != 0)).map_or(0, |f| f + 1);
+
+    let mut synth = Synth::new(waves, sounds, rate);
+    let mut out : Vec<i16> = vec![];
+
+    // rows move on by tempo * 6 / speed per 900 ticks
+
+    let per_minute = (t.tempo.max(1) as i64) * 6;
+    let speed = t.speed.max(1) as i64;
+
+    // the rows byte wraps, so odd carts can claim more rows than a pattern has
+
+    let rows = t.rows.clamp(1, PATTERN_ROWS);
+
+    let mut tick : i64 = 0;
+    let mut last_row : Option<usize> = None;
+
+    loop {
+        let row = (tick * per_minute / speed / NOTES_PER_MINUTE) as usize;
+        let (frame, r) = (row / rows, row % rows);
+
+        if frame >= frames {
+            break
+        }
+
+        if last_row != Some(row) {
+            last_row = Some(row);
+
+            for c in 0..CHANNELS {
+                let p = t.frames[frame][c] as usize;
+
+                if p == 0 || p > patterns.patterns.len() {
+                    continue
+                }
+
+                let cell = patterns.patterns[p - 1][r];
+
+                if cell.command == 1 {
+                    synth.set_volume(c, (cell.param1 + cell.param2) as f32 / (2 * WAVE_MAX) as f32);
+                }
+
+                match cell.note {
+                    NOTE_STOP => synth.stop(c),
+                    n if n >= NOTE_FIRST => synth.play(c, cell.sfx as usize, Some(cell.octave as i32 * 12 + (n - NOTE_FIRST) as i32)),
+                    _ => {},
+                }
+            }
+        }
+
+        synth.tick(&mut out);
+        tick += 1;
+    }
+
+    out
+}
@@ -0,0 +1,34 @@
+// bare bones 16 bit mono pcm .wav writing, for listening to sounds
+// without having TIC-80 (or any audio device) around
+
+pub const SAMPLE_RATE : u32 = 44100;
+
+pub fn encode_wav(samples: &[i16], rate: u32) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+
+    let mut out : Vec<u8> = vec![];
+
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+
+    // format: pcm, 1 channel, 2 bytes per frame
+
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&rate.to_le_bytes());
+    out.extend_from_slice(&(rate * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+
+    for s in samples {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+
+    out
+}
@@ -0,0 +1,87 @@
+// 16 waveforms of 32 4 bit samples, packed two to a byte low nibble first
+
+pub const WAVE_COUNT : usize = 16;
+pub const WAVE_SAMPLES : usize = 32;
+pub const WAVE_MAX : u8 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Square,
+    Saw,
+    Triangle,
+    Noise,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Waves {
+    pub waves : Vec<Vec<u8>>,
+}
+
+impl Default for Waves {
+    fn default() -> Waves {
+        Waves { waves : vec![vec![0; WAVE_SAMPLES]; WAVE_COUNT] }
+    }
+}
+
+impl Waves {
+    pub fn from_chunk(data: &[u8]) -> Waves {
+        let mut waves = Waves::default();
+
+        for (i, b) in data.iter().take(WAVE_COUNT * WAVE_SAMPLES / 2).enumerate() {
+            let wave = &mut waves.waves[i * 2 / WAVE_SAMPLES];
+
+            wave[i * 2 % WAVE_SAMPLES] = b & 15;
+            wave[i * 2 % WAVE_SAMPLES + 1] = b >> 4;
+        }
+
+        waves
+    }
+
+    // trailing zeros are dropped, like TIC-80 does
+
+    pub fn to_chunk(&self) -> Vec<u8> {
+        let bytes : Vec<u8> = self.waves.iter().flat_map(|w| w.chunks(2).map(|p| (p[0] & 15) | (p[1] << 4))).collect();
+        let used = bytes.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+
+        bytes[..used].to_vec()
+    }
+}
+
+// `seed` only matters for noise, the same seed gives the same wave
+
+pub fn preset(kind: Preset, seed: u32) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(2654435761).wrapping_add(1);
+
+    (0..WAVE_SAMPLES).map(|i| match kind {
+        Preset::Square => match i < WAVE_SAMPLES / 2 {
+            true => WAVE_MAX,
+            false => 0,
+        },
+        Preset::Saw => (i / 2) as u8,
+        Preset::Triangle => match i < WAVE_SAMPLES / 2 {
+            true => i as u8,
+            false => (WAVE_SAMPLES - 1 - i) as u8,
+        },
+        Preset::Noise => {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            (state >> 28) as u8
+        },
+    }).collect()
+}
+
+// the wave looped at `freq` hz for `seconds`, centred around zero
+
+pub fn preview(wave: &[u8], freq: f32, seconds: f32, rate: u32) -> Vec<i16> {
+    let count = (seconds * rate as f32) as usize;
+    let amp = i16::MAX as f32 / 2.0;
+
+    (0..count).map(|n| {
+        let pos = (n as f32 * freq * WAVE_SAMPLES as f32 / rate as f32) as usize % WAVE_SAMPLES;
+        let level = wave[pos] as f32 / WAVE_MAX as f32 * 2.0 - 1.0;
+
+        (level * amp) as i16
+    }).collect()
+}
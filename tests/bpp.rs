@@ -0,0 +1,84 @@
+use artic_editor2::cartridge::{SPR_PIXELS, pack_sheet, unpack_sheet};
+use artic_editor2::palette::swap_permutation;
+use artic_editor2::sheet::{SHEET_BYTES, SHEET_CELLS, bpp_page, bpp_pages, pack_bpp, remap_bpp_page, set_bpp_page, unpack_bpp};
+
+#[test]
+fn four_bpp_matches_the_sheet_loader() {
+    let data : Vec<u8> = (0..SHEET_BYTES).map(|i| (i * 7) as u8).collect();
+
+    assert_eq!(unpack_bpp(&data, 4), unpack_sheet(&data));
+    assert_eq!(pack_bpp(&unpack_sheet(&data), 4), pack_sheet(&unpack_sheet(&data)));
+}
+
+#[test]
+fn lower_bpp_reads_low_bits_first() {
+    let two = unpack_bpp(&[0b11_10_01_00], 2);
+    let one = unpack_bpp(&[0b1000_0001], 1);
+
+    assert_eq!(two.len(), SHEET_CELLS * 2);
+    assert_eq!(one.len(), SHEET_CELLS * 4);
+
+    assert_eq!(&two[0][..5], &[0, 1, 2, 3, 0]);
+    assert_eq!(&one[0][..8], &[1, 0, 0, 0, 0, 0, 0, 1]);
+
+    for bpp in [4, 2, 1] {
+        let data : Vec<u8> = (0..SHEET_BYTES).map(|i| (i * 13 + 5) as u8).collect();
+
+        assert_eq!(pack_bpp(&unpack_bpp(&data, bpp), bpp), data, "{}bpp", bpp);
+        assert_eq!(bpp_pages(bpp) * SHEET_CELLS, unpack_bpp(&data, bpp).len());
+    }
+}
+
+#[test]
+fn pages_write_back_into_the_sheet() {
+    let mut sheet = vec![vec![5; SPR_PIXELS]];
+
+    // the second 2bpp page lives in the bottom half of the 4bpp sheet
+
+    let mut page = bpp_page(&sheet, 2, 1);
+    assert!(page.iter().flatten().all(|&i| i == 0));
+
+    page[0][0] = 3;
+    page[0][1] = 7;
+
+    set_bpp_page(&mut sheet, &page, 2, 1);
+
+    assert_eq!(sheet.len(), SHEET_CELLS / 2 + 1);
+    assert_eq!(sheet[0], vec![5; SPR_PIXELS]);
+    assert_eq!(&sheet[SHEET_CELLS / 2][..2], &[0b1111, 0]);
+    assert_eq!(bpp_page(&sheet, 2, 1)[0][..2], [3, 3]);
+
+    // an untouched page leaves the sheet as short as it was
+
+    let mut short = vec![vec![1; SPR_PIXELS]];
+    let first = bpp_page(&short, 1, 0);
+
+    set_bpp_page(&mut short, &first, 1, 0);
+
+    assert_eq!(short, vec![vec![1; SPR_PIXELS]]);
+}
+
+#[test]
+fn swaps_at_2bpp_only_touch_their_page() {
+    let mut sheet = vec![vec![0; SPR_PIXELS]; SHEET_CELLS];
+
+    let mut first = bpp_page(&sheet, 2, 0);
+    let mut second = bpp_page(&sheet, 2, 1);
+
+    first[0][..4].copy_from_slice(&[0, 1, 2, 3]);
+    second[0][..4].copy_from_slice(&[1, 1, 2, 2]);
+
+    set_bpp_page(&mut sheet, &first, 2, 0);
+    set_bpp_page(&mut sheet, &second, 2, 1);
+
+    remap_bpp_page(&mut sheet, &swap_permutation(1, 2), 2, 0);
+
+    assert_eq!(bpp_page(&sheet, 2, 0)[0][..4], [0, 2, 1, 3]);
+    assert_eq!(bpp_page(&sheet, 2, 1), second);
+
+    // swapping back restores the bytes
+
+    remap_bpp_page(&mut sheet, &swap_permutation(1, 2), 2, 0);
+
+    assert_eq!(bpp_page(&sheet, 2, 0), first);
+}
@@ -0,0 +1,102 @@
+use artic_editor2::cartridge::{BANK_SIZE, CartError, Cartridge, Chunk, ChunkType};
+use artic_editor2::code::{TextBuffer, TokenKind, highlight_line};
+
+#[test]
+fn code_banks_are_joined_and_split() {
+    let cart = Cartridge::from_chunks(vec![
+        Chunk::new(ChunkType::Code, 1, b"-- hello".to_vec()),
+        Chunk::new(ChunkType::Code, 0, b" world".to_vec()),
+    ]);
+
+    // like TIC-80, the highest bank holds the start of the code
+
+    assert_eq!(cart.code().unwrap(), "-- hello world");
+
+    // a bit more than two banks worth of code
+
+    let long = format!("{}{}", "x".repeat(BANK_SIZE * 2), "y".repeat(10));
+    let mut cart = Cartridge::new();
+
+    cart.set_code(&long).unwrap();
+
+    assert_eq!(cart.banks(ChunkType::Code), vec![0, 1, 2]);
+    assert_eq!(cart.get(ChunkType::Code, 0).unwrap().data, b"y".repeat(10));
+    assert_eq!(cart.get(ChunkType::Code, 2).unwrap().data.len(), BANK_SIZE);
+    assert_eq!(cart.code().unwrap(), long);
+
+    let back = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(back.code().unwrap(), long);
+
+    // shrinking drops the banks that are no longer used
+
+    cart.set_code("print(1)").unwrap();
+
+    assert_eq!(cart.banks(ChunkType::Code), vec![0]);
+    assert!(matches!(cart.set_code(&"x".repeat(BANK_SIZE * 8 + 1)), Err(CartError::CodeTooLarge(_))));
+}
+
+#[test]
+fn line_endings_and_encoding_are_kept() {
+    let crlf = "a = 1\r\nb = 2\r\n";
+    let mut buf = TextBuffer::new(crlf);
+
+    assert_eq!(buf.text(), crlf);
+
+    buf.insert('x');
+
+    assert_eq!(buf.text(), "xa = 1\r\nb = 2\r\n");
+    assert_eq!(TextBuffer::new("a\nb").text(), "a\nb");
+
+    // latin-1 can't be edited as text without changing it
+
+    let latin = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Code, 0, b"-- caf\xe9".to_vec())]);
+
+    assert!(matches!(latin.code(), Err(CartError::CodeNotUtf8 { offset : 6 })));
+}
+
+#[test]
+fn lua_is_highlighted() {
+    let (tokens, open) = highlight_line("local s = \"a\\\"b\" -- hi", false);
+    let kinds : Vec<TokenKind> = tokens.iter().filter(|t| t.kind != TokenKind::Space).map(|t| t.kind).collect();
+
+    assert_eq!(kinds, vec![TokenKind::Keyword, TokenKind::Ident, TokenKind::Symbol, TokenKind::Str, TokenKind::Comment]);
+    assert!(!open);
+
+    // block comments carry over to the next line
+
+    let (_, open) = highlight_line("x = 1 --[[ start", false);
+    assert!(open);
+
+    let (tokens, open) = highlight_line("end ]] y", true);
+    assert_eq!(tokens[0].kind, TokenKind::Comment);
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::Ident);
+    assert!(!open);
+}
+
+#[test]
+fn editing_find_and_replace() {
+    let mut buf = TextBuffer::new("a = 1\nb = a");
+
+    buf.set_cursor(0, 5);
+    buf.newline();
+    buf.insert_str("c");
+
+    assert_eq!(buf.text(), "a = 1\nc\nb = a");
+
+    buf.backspace();
+    buf.backspace();
+
+    assert_eq!(buf.text(), "a = 1\nb = a");
+
+    // find wraps around past the end
+
+    buf.set_cursor(1, 4);
+
+    assert!(buf.find("a"));
+    assert_eq!((buf.line, buf.col), (0, 0));
+    assert!(!buf.find("zzz"));
+
+    assert_eq!(buf.replace_all("a", "foo"), 2);
+    assert_eq!(buf.text(), "foo = 1\nb = foo");
+}
@@ -0,0 +1,35 @@
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+use artic_editor2::flags::{FLAG_COUNT, Flags, SPRITE_FLAGS};
+
+#[test]
+fn flags_round_trip_through_the_chunk() {
+    let data = vec![0b1000_0001, 0, 4];
+    let cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Flags, 0, data.clone())]);
+
+    let flags = cart.flags();
+
+    assert_eq!(flags.bits.len(), FLAG_COUNT);
+    assert!(flags.get(0, 0) && flags.get(0, 7) && flags.get(2, 2));
+    assert!(!flags.get(1, 0));
+    assert_eq!(flags.to_chunk(), data);
+}
+
+#[test]
+fn selection_toggles_together() {
+    let mut flags = Flags::default();
+    let ids = [3, 4, SPRITE_FLAGS + 3];
+
+    flags.toggle(4, 5);
+    flags.toggle_all(&ids, 5);
+
+    assert_eq!(flags.count(&ids, 5), 3);
+
+    flags.toggle_all(&ids, 5);
+
+    assert_eq!(flags.count(&ids, 5), 0);
+
+    let mut cart = Cartridge::new();
+    cart.set_flags(&flags);
+
+    assert!(!cart.has(ChunkType::Flags));
+}
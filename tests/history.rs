@@ -0,0 +1,85 @@
+use artic_editor2::history::{Edit, History, HISTORY_LIMIT};
+
+fn stroke(from: u8, to: u8) -> Edit {
+    Edit::Selection { before : vec![vec![from; 64]], after : vec![vec![to; 64]] }
+}
+
+fn sheet(sprites: bool, from: u8, to: u8) -> Edit {
+    Edit::Sheet { sprites, bank : 0, bpp : 4, page : 0, before : vec![vec![from; 64]], after : vec![vec![to; 64]] }
+}
+
+#[test]
+fn undo_and_redo_step_back_and_forth() {
+    let mut history = History::default();
+
+    history.push(sheet(false, 0, 1));
+    history.push(sheet(true, 0, 2));
+    history.push(Edit::Palette { ovr : false, before : vec![(0, 0, 0)], after : vec![(9, 9, 9)] });
+
+    // undo hands back the edit reversed, so `after` is always what to apply
+
+    assert_eq!(history.undo(|_| true), Some(Edit::Palette { ovr : false, before : vec![(9, 9, 9)], after : vec![(0, 0, 0)] }));
+    assert_eq!(history.undo(|_| true), Some(sheet(true, 2, 0)));
+    assert_eq!(history.redo(|_| true), Some(sheet(true, 0, 2)));
+
+    // tiles and sprites share one history
+
+    assert_eq!(history.undo(|_| true), Some(sheet(true, 2, 0)));
+    assert_eq!(history.undo(|_| true), Some(sheet(false, 1, 0)));
+    assert_eq!(history.undo(|_| true), None);
+
+    // a fresh edit drops what was undone
+
+    history.push(stroke(3, 4));
+
+    assert_eq!(history.redo(|_| true), None);
+    assert_eq!(history.done, vec![stroke(3, 4)]);
+}
+
+#[test]
+fn filtered_and_noop_edits() {
+    let mut history = History::default();
+
+    history.push(stroke(1, 1));
+    history.push(Edit::Swap { ovr : false, a : 2, b : 2, page : None });
+
+    assert!(history.done.is_empty());
+
+    history.push(sheet(false, 0, 1));
+
+    assert_eq!(history.undo(|e| !matches!(e, Edit::Sheet { .. })), None);
+    assert_eq!(history.done.len(), 1);
+
+    // swaps are their own undo
+
+    history.push(Edit::Swap { ovr : true, a : 1, b : 5, page : None });
+
+    assert_eq!(history.undo(|_| true), Some(Edit::Swap { ovr : true, a : 1, b : 5, page : None }));
+
+    for i in 0..HISTORY_LIMIT + 10 {
+        history.push(sheet(false, (i % 250) as u8, (i % 250) as u8 + 1));
+    }
+
+    assert_eq!(history.done.len(), HISTORY_LIMIT);
+    assert_eq!(history.done[0], sheet(false, 10, 11));
+}
+
+#[test]
+fn squash_turns_strokes_into_one_sheet_edit() {
+    let mut history = History::default();
+
+    history.push(sheet(true, 0, 1));
+    history.push(stroke(0, 5));
+    history.push(Edit::Palette { ovr : true, before : vec![(1, 2, 3)], after : vec![(4, 5, 6)] });
+    history.push(stroke(5, 6));
+
+    history.undo(|_| true);
+    history.squash(sheet(false, 0, 6));
+
+    assert_eq!(history.done, vec![
+        sheet(true, 0, 1),
+        Edit::Palette { ovr : true, before : vec![(1, 2, 3)], after : vec![(4, 5, 6)] },
+        sheet(false, 0, 6),
+    ]);
+    assert!(history.undone.is_empty());
+}
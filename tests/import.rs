@@ -0,0 +1,87 @@
+use artic_editor2::bitmap::Bitmap;
+use artic_editor2::cartridge::DEFAULT_PALETTE;
+use artic_editor2::quantise::quantise;
+use artic_editor2::sheet::{place, render, render_sheet, slice};
+
+fn palette() -> Vec<(u8, u8, u8)> {
+    DEFAULT_PALETTE.chunks(3).map(|c| (c[0], c[1], c[2])).collect()
+}
+
+#[test]
+fn palette_colours_map_to_themselves() {
+    let pal = palette();
+    let mut img = Bitmap::new(16, 1, (0, 0, 0, 255));
+
+    for (i, c) in pal.iter().enumerate() {
+        img.set_pixel(i as u32, 0, (c.0, c.1, c.2, 255));
+    }
+
+    let expected : Vec<u8> = (0..16).collect();
+
+    assert_eq!(quantise(&img, &pal, false), expected);
+    assert_eq!(quantise(&img, &pal, true), expected);
+}
+
+#[test]
+fn transparent_pixels_become_colour_zero() {
+    let img = Bitmap::new(2, 2, (255, 255, 255, 0));
+
+    assert_eq!(quantise(&img, &palette(), false), vec![0; 4]);
+}
+
+#[test]
+fn dithering_mixes_colours() {
+    let pal = vec![(0, 0, 0), (255, 255, 255)];
+    let img = Bitmap::new(8, 8, (128, 128, 128, 255));
+
+    let flat = quantise(&img, &pal, false);
+    let dithered = quantise(&img, &pal, true);
+
+    assert!(flat.iter().all(|&i| i == flat[0]));
+    assert!(dithered.contains(&0) && dithered.contains(&1));
+}
+
+#[test]
+fn blocks_keep_their_layout_on_the_sheet() {
+    // 12x9 pixels -> 2x2 blocks, numbered by the block they land in
+    let (w, h) = (12, 9);
+    let pixels : Vec<u8> = (0..w * h).map(|i| ((i % w) / 8 + (i / w) / 8 * 2) as u8 + 1).collect();
+
+    let (blocks, cols) = slice(&pixels, w, h);
+
+    assert_eq!((blocks.len(), cols), (4, 2));
+    assert_eq!(blocks[3][0], 4);
+    assert_eq!((blocks[3][4], blocks[3][8]), (0, 0));
+
+    let mut sheet : Vec<Vec<u8>> = vec![];
+    place(&mut sheet, &blocks, cols, 15);
+
+    // the second column falls off the right edge
+    assert_eq!(sheet.len(), 32);
+    assert_eq!(sheet[15][0], 1);
+    assert_eq!(sheet[31][0], 3);
+    assert_eq!(sheet[16], vec![0; 64]);
+}
+
+#[test]
+fn exported_sheet_goes_through_the_palette() {
+    let pal = palette();
+
+    let mut sheet : Vec<Vec<u8>> = vec![];
+    place(&mut sheet, &[vec![3; 64]], 1, 17);
+
+    let img = render_sheet(&sheet, &pal, true, 2);
+
+    assert_eq!((img.width, img.height), (256, 256));
+
+    let c = pal[3];
+
+    assert_eq!(img.pixel(0, 0).3, 0);
+    assert_eq!(img.pixel(16, 16), (c.0, c.1, c.2, 255));
+    assert_eq!(img.pixel(31, 31), (c.0, c.1, c.2, 255));
+    assert_eq!(img.pixel(32, 32).3, 0);
+
+    let opaque = render(&[vec![0; 64]], 1, &pal, false, 1);
+
+    assert_eq!(opaque.pixel(7, 7), (pal[0].0, pal[0].1, pal[0].2, 255));
+}
@@ -0,0 +1,58 @@
+use std::{fs, path::PathBuf};
+
+use artic_editor2::cartridge::{Cartridge, ChunkType};
+use artic_editor2::map::{MAP_HEIGHT, MAP_WIDTH, Map, Stamp};
+
+fn gmtk() -> Cartridge {
+    Cartridge::from_bytes(&fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/GMTK2023.tic")).unwrap()).unwrap()
+}
+
+#[test]
+fn map_chunk_round_trips() {
+    let cart = gmtk();
+    let before = cart.get(ChunkType::Map, 0).unwrap().clone();
+
+    let mut edited = cart.clone();
+    edited.set_map(&cart.map());
+
+    assert_eq!(edited.get(ChunkType::Map, 0), Some(&before));
+    assert_eq!(edited.to_bytes().unwrap(), cart.to_bytes().unwrap());
+}
+
+#[test]
+fn stamps_are_clipped_to_the_map() {
+    let mut map = Map::default();
+    let stamp = Stamp::from_sheet(17, 2);
+
+    assert_eq!((stamp.width, stamp.height), (2, 2));
+    assert_eq!(stamp.tiles, vec![1, 2, 17, 18]);
+
+    map.stamp(MAP_WIDTH - 1, MAP_HEIGHT - 1, &stamp);
+
+    assert_eq!(map.get(MAP_WIDTH - 1, MAP_HEIGHT - 1), 1);
+    assert_eq!(map.to_chunk().len(), MAP_WIDTH * MAP_HEIGHT);
+}
+
+#[test]
+fn empty_map_drops_the_chunk() {
+    let mut cart = gmtk();
+
+    cart.set_map(&Map::default());
+
+    assert!(!cart.has(ChunkType::Map));
+}
+
+#[test]
+fn render_uses_tiles_and_palette() {
+    let cart = gmtk();
+    let (map, tiles, palette) = (cart.map(), cart.tiles(), cart.palette());
+
+    let img = map.render(&tiles, &palette, MAP_WIDTH - 2, 0, 30, 17);
+
+    assert_eq!((img.width, img.height), (16, 136));
+
+    let tile = &tiles[map.get(MAP_WIDTH - 1, 1) as usize];
+    let col = palette[tile[3 + 2 * 8] as usize];
+
+    assert_eq!(img.pixel(8 + 3, 8 + 2), (col.0, col.1, col.2, 255));
+}
@@ -0,0 +1,66 @@
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+use artic_editor2::music::{FRAME_COUNT, Music, NOTE_FIRST, PATTERN_COUNT, PATTERN_ROWS, Patterns, ROW_BYTES, Row, TRACK_BYTES, TRACK_COUNT, Track};
+
+#[test]
+fn rows_unpack_every_column() {
+    let row = Row { note : NOTE_FIRST + 9, octave : 5, sfx : 42, command : 3, param1 : 0xa, param2 : 0x7 };
+    let bytes = row.encode();
+
+    assert_eq!(bytes, [0xad, 0xb7, 0xaa]);
+    assert_eq!(Row::decode(&bytes), row);
+    assert_eq!(row.note_label(), "A-5");
+
+    // every 3 byte combination survives
+
+    for a in (0..=255u8).step_by(7) {
+        for b in 0..=255u8 {
+            let raw = [a, b, a ^ b];
+            assert_eq!(Row::decode(&raw).encode(), raw);
+        }
+    }
+}
+
+#[test]
+fn tracks_pack_frames_and_settings() {
+    let mut raw = vec![0; TRACK_BYTES];
+
+    // channel 0 plays pattern 1, channel 3 pattern 60
+
+    raw[0] = 1;
+    raw[2] = 60 << 2;
+    raw[FRAME_COUNT * 3] = (-10i8) as u8;
+    raw[FRAME_COUNT * 3 + 1] = 32;
+    raw[FRAME_COUNT * 3 + 2] = 2;
+
+    let track = Track::decode(&raw);
+
+    assert_eq!(track.frames[0], [1, 0, 0, 60]);
+    assert_eq!((track.tempo, track.rows, track.speed), (140, 32, 8));
+    assert_eq!(track.encode(), raw);
+
+    let track = Track::default();
+
+    assert_eq!((track.tempo, track.rows, track.speed), (150, 64, 6));
+}
+
+#[test]
+fn chunks_round_trip_exactly() {
+    let pats : Vec<u8> = (0..PATTERN_ROWS * ROW_BYTES * 2 + 5).map(|i| (i * 13 % 256) as u8 | 1).collect();
+    let tracks : Vec<u8> = (0..TRACK_BYTES * 3).map(|i| (i * 7 % 256) as u8 | 1).collect();
+
+    let mut cart = Cartridge::from_chunks(vec![
+        Chunk::new(ChunkType::Patterns, 0, pats.clone()),
+        Chunk::new(ChunkType::Music, 0, tracks.clone()),
+    ]);
+
+    let (patterns, music) = (cart.patterns(), cart.music());
+
+    assert_eq!((patterns.patterns.len(), music.tracks.len()), (PATTERN_COUNT, TRACK_COUNT));
+    assert_eq!(patterns.to_chunk(), pats);
+    assert_eq!(music.to_chunk(), tracks);
+
+    cart.set_patterns(&Patterns::default());
+    cart.set_music(&Music::default());
+
+    assert!(!cart.has(ChunkType::Patterns) && !cart.has(ChunkType::Music));
+}
@@ -0,0 +1,144 @@
+use artic_editor2::cartridge::{CartError, Cartridge, Chunk, ChunkType, DEFAULT_PALETTE};
+use artic_editor2::palette::{PALETTE_FORMATS, PaletteFormat, fit_palette, hsv_to_rgb, is_permutation, parse_hex, permute_palette, read_palette, remap_pixels, rgb_to_hsv, swap_permutation, to_hex, write_palette};
+
+#[test]
+fn hsv_round_trips_every_palette_colour() {
+    for c in DEFAULT_PALETTE.chunks(3) {
+        let col = (c[0], c[1], c[2]);
+
+        assert_eq!(hsv_to_rgb(rgb_to_hsv(col)), col);
+    }
+
+    assert_eq!(rgb_to_hsv((255, 0, 0)), (0.0, 1.0, 1.0));
+    assert_eq!(hsv_to_rgb((120.0, 1.0, 1.0)), (0, 255, 0));
+    assert_eq!(hsv_to_rgb((240.0, 0.0, 0.5)), (128, 128, 128));
+}
+
+#[test]
+fn hex_entry() {
+    assert_eq!(parse_hex("#1a1c2c"), Some((26, 28, 44)));
+    assert_eq!(parse_hex("FFcd75"), Some((255, 205, 117)));
+    assert_eq!(parse_hex("12345"), None);
+    assert_eq!(parse_hex("12345g"), None);
+    assert_eq!(to_hex((26, 28, 44)), "1a1c2c");
+}
+
+#[test]
+fn edited_palette_replaces_the_default() {
+    let mut cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Default, 0, vec![])]);
+    let mut palette = cart.palette();
+
+    assert_eq!(palette[0], (26, 28, 44));
+
+    palette[0] = (1, 2, 3);
+    cart.set_palette(&palette);
+
+    let back = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(back.palette(), palette);
+    assert_eq!(back.get(ChunkType::Palette, 0).unwrap().data[..3], [1, 2, 3]);
+}
+
+#[test]
+fn scn_and_ovr_are_kept_apart() {
+    let data : Vec<u8> = (0..96).map(|i| i as u8 + 1).collect();
+    let mut cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Palette, 0, data.clone())]);
+
+    let (scn, ovr) = (cart.palette(), cart.ovr_palette());
+
+    assert_eq!((scn.len(), ovr.len()), (16, 16));
+    assert_eq!((scn[0], ovr[0]), ((1, 2, 3), (49, 50, 51)));
+
+    // editing one leaves the other's bytes alone
+
+    let mut edited = scn.clone();
+    edited[15] = (0, 0, 0);
+    cart.set_palette(&edited);
+
+    let after = cart.get(ChunkType::Palette, 0).unwrap().data.clone();
+
+    assert_eq!(after[48..], data[48..]);
+    assert_eq!(after[45..48], [0, 0, 0]);
+
+    cart.set_ovr_palette(&scn);
+
+    assert_eq!(cart.ovr_palette(), scn);
+    assert_eq!(cart.palette(), edited);
+
+    // a 48 byte chunk grows to hold the ovr palette
+
+    let mut cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Palette, 0, data[..48].to_vec())]);
+
+    assert!(cart.ovr_palette().iter().all(|&c| c == (0, 0, 0)));
+
+    cart.set_ovr_palette(&ovr);
+
+    assert_eq!(cart.get(ChunkType::Palette, 0).unwrap().data, data);
+}
+
+#[test]
+fn palette_files_round_trip() {
+    let colours : Vec<(u8, u8, u8)> = DEFAULT_PALETTE.chunks(3).map(|c| (c[0], c[1], c[2])).collect();
+
+    for format in PALETTE_FORMATS {
+        let bytes = write_palette(&colours, format).unwrap();
+
+        assert_eq!(read_palette(&bytes, format).unwrap(), colours, "{:?}", format);
+    }
+
+    assert_eq!(PaletteFormat::from_path("art/sweetie.GPL"), Some(PaletteFormat::Gimp));
+    assert_eq!(PaletteFormat::from_path("cart.tic"), None);
+}
+
+#[test]
+fn palette_files_from_other_tools() {
+    let jasc = "JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 0 255\r\n";
+    let gimp = "GIMP Palette\nName: Two\nColumns: 2\n# a comment\n255   0   0\tRed\n  0   0 255\tBlue\n";
+    let hex = "ff0000\n0000FF\n";
+
+    for (text, format) in [(jasc, PaletteFormat::Jasc), (gimp, PaletteFormat::Gimp), (hex, PaletteFormat::Hex)] {
+        assert_eq!(read_palette(text.as_bytes(), format).unwrap(), vec![(255, 0, 0), (0, 0, 255)]);
+    }
+
+    assert!(matches!(read_palette(b"ff0000\nnope\n", PaletteFormat::Hex), Err(CartError::BadPalette { line : 2, .. })));
+}
+
+#[test]
+fn big_palettes_are_cut_to_16() {
+    let many : Vec<(u8, u8, u8)> = (0..20).map(|i| (i, i, i)).collect();
+
+    let (fitted, dropped) = fit_palette(&many);
+
+    assert_eq!((fitted.len(), dropped), (16, 4));
+    assert_eq!(fitted[15], (15, 15, 15));
+
+    let (fitted, dropped) = fit_palette(&many[..3]);
+
+    assert_eq!((fitted[3], dropped), ((0, 0, 0), 0));
+}
+
+#[test]
+fn permuting_keeps_the_art_looking_the_same() {
+    let palette : Vec<(u8, u8, u8)> = (0..16).map(|i| (i * 10, 0, 0)).collect();
+    let mut pixels : Vec<u8> = vec![0, 3, 5, 3, 15];
+
+    let before : Vec<(u8, u8, u8)> = pixels.iter().map(|&p| palette[p as usize]).collect();
+
+    // rotate every colour one place up
+
+    let perm : Vec<u8> = (0..16).map(|i| (i + 1) % 16).collect();
+
+    assert!(is_permutation(&perm));
+    assert!(!is_permutation(&[0; 16]));
+
+    let moved = permute_palette(&palette, &perm);
+    remap_pixels(&mut pixels, &perm);
+
+    assert_eq!(pixels, vec![1, 4, 6, 4, 0]);
+    assert_eq!(pixels.iter().map(|&p| moved[p as usize]).collect::<Vec<(u8, u8, u8)>>(), before);
+
+    let swap = swap_permutation(2, 9);
+
+    assert_eq!((swap[2], swap[9], swap[3]), (9, 2, 3));
+    assert_eq!(permute_palette(&palette, &swap)[9], palette[2]);
+}
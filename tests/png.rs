@@ -0,0 +1,106 @@
+use std::{fs, path::PathBuf};
+
+use artic_editor2::bitmap::Bitmap;
+use artic_editor2::cartridge::{Cartridge, is_png, zip};
+
+fn test_cart() -> Vec<u8> {
+    fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/GMTK2023.tic")).unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("artic_png_{}_{}", std::process::id(), name))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+
+    for &b in data {
+        crc ^= b as u32;
+
+        for _k in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+
+    !crc
+}
+
+#[test]
+fn png_cart_round_trips() {
+    let tic = test_cart();
+    let cart = Cartridge::from_bytes(&tic).unwrap();
+
+    let path = temp_path("cart.png");
+    let p = path.to_str().unwrap();
+
+    cart.save(p).unwrap();
+
+    let written = fs::read(&path).unwrap();
+    let reread = Cartridge::load(p).unwrap();
+    let _ = fs::remove_file(&path);
+
+    assert!(is_png(&written));
+    assert_eq!(reread.to_bytes().unwrap(), tic);
+    assert!(reread.cover().is_some());
+}
+
+#[test]
+fn png_cart_keeps_its_cover() {
+    let mut cover = Bitmap::new(300, 200, (200, 30, 90, 255));
+    cover.set_pixel(10, 10, (1, 2, 3, 255));
+
+    let mut cart = Cartridge::from_bytes(&test_cart()).unwrap();
+    cart.set_cover(Some(cover.clone()));
+
+    let path = temp_path("cover.png");
+    let p = path.to_str().unwrap();
+
+    cart.save(p).unwrap();
+
+    let reread = Cartridge::load(p).unwrap();
+    let _ = fs::remove_file(&path);
+
+    let kept = reread.cover().unwrap();
+
+    assert_eq!((kept.width, kept.height), (300, 200));
+
+    // only the low bits may change
+    for (a, b) in kept.rgba.iter().zip(&cover.rgba) {
+        assert!(a.abs_diff(*b) < 16);
+    }
+}
+
+#[test]
+fn legacy_cart_chunk_is_read() {
+    let tic = test_cart();
+    let plain = Bitmap::new(16, 16, (0, 0, 0, 255)).encode_png().unwrap();
+
+    // IHDR is always first: signature(8) + len(4) + name(4) + data(13) + crc(4)
+    let at = 8 + 4 + 4 + 13 + 4;
+
+    let data = zip(&tic);
+
+    let mut chunk : Vec<u8> = vec![];
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(b"caRt");
+    chunk.extend_from_slice(&data);
+    chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+
+    let mut png = plain[..at].to_vec();
+    png.extend_from_slice(&chunk);
+    png.extend_from_slice(&plain[at..]);
+
+    let cart = Cartridge::from_bytes(&png).unwrap();
+
+    assert_eq!(cart.to_bytes().unwrap(), tic);
+}
+
+#[test]
+fn plain_png_is_not_a_cart() {
+    let plain = Bitmap::new(64, 64, (0, 0, 0, 255)).encode_png().unwrap();
+
+    assert!(Cartridge::from_bytes(&plain).is_err());
+}
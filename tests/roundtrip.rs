@@ -0,0 +1,306 @@
+use std::{fs, path::PathBuf};
+
+use artic_editor2::cartridge::{BANK_SIZE, HEADER_SIZE, CartError, Cartridge, Chunk, ChunkReader, ChunkType, deconstruct_tic, construct_tic, parse_tic, write_tic, zip};
+
+// every .tic shipped next to the sources
+
+fn bundled_carts() -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src");
+
+    let mut carts : Vec<PathBuf> = fs::read_dir(dir).unwrap()
+        .map(|i| i.unwrap().path())
+        .filter(|i| i.extension().is_some_and(|e| e == "tic"))
+        .collect();
+
+    carts.sort();
+
+    assert!(!carts.is_empty(), "no bundled carts found");
+
+    carts
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("artic_roundtrip_{}_{}", std::process::id(), name))
+}
+
+// describe how two chunk lists differ, chunk by chunk
+
+fn chunk_diff(before: &[Chunk], after: &[Chunk]) -> String {
+    let mut out = String::new();
+
+    if before.len() != after.len() {
+        out += &format!("chunk count {} -> {}\n", before.len(), after.len());
+    }
+
+    for idx in 0..before.len().max(after.len()) {
+        match (before.get(idx), after.get(idx)) {
+            (Some(a), Some(b)) if a == b => {},
+            (Some(a), Some(b)) => {
+                out += &format!("#{} {} bank {} ({} bytes) -> {} bank {} ({} bytes)", idx, a.kind, a.bank, a.data.len(), b.kind, b.bank, b.data.len());
+
+                if let Some(at) = a.data.iter().zip(&b.data).position(|(x, y)| x != y) {
+                    out += &format!(", first differing byte at {}: {} -> {}", at, a.data[at], b.data[at]);
+                }
+
+                out += "\n";
+            },
+            (Some(a), None) => out += &format!("#{} {} bank {} went missing\n", idx, a.kind, a.bank),
+            (None, Some(b)) => out += &format!("#{} {} bank {} appeared\n", idx, b.kind, b.bank),
+            (None, None) => {},
+        }
+    }
+
+    out
+}
+
+fn assert_same(name: &str, before: &[u8], after: &[u8]) {
+    if before == after {
+        return
+    }
+
+    let diff = chunk_diff(&parse_tic(before).unwrap(), &parse_tic(after).unwrap());
+
+    panic!("{} changed on round-trip ({} -> {} bytes)\n{}", name, before.len(), after.len(), diff);
+}
+
+#[test]
+fn bundled_carts_survive_construct_tic() {
+    for path in bundled_carts() {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        let original = fs::read(&path).unwrap();
+        let chunks = deconstruct_tic(path.to_str().unwrap()).unwrap();
+
+        let out = temp_path(&name);
+        let _ = fs::remove_file(&out);
+
+        construct_tic(out.to_str().unwrap(), &chunks).unwrap();
+
+        let written = fs::read(&out).unwrap();
+        let _ = fs::remove_file(&out);
+
+        assert_same(&name, &original, &written);
+    }
+}
+
+#[test]
+fn bundled_carts_survive_in_memory() {
+    for path in bundled_carts() {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        let original = fs::read(&path).unwrap();
+        let cart = Cartridge::from_bytes(&original).unwrap();
+
+        assert_same(&name, &original, &cart.to_bytes().unwrap());
+    }
+}
+
+#[test]
+fn untouched_chunks_survive_sheet_edits() {
+    for path in bundled_carts() {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        let original = Cartridge::from_bytes(&fs::read(&path).unwrap()).unwrap();
+
+        let mut cart = original.clone();
+        let mut tiles = cart.tiles();
+
+        if let Some(t) = tiles.first_mut() {
+            t[0] = (t[0] + 1) % 16;
+        }
+
+        cart.set_tiles(&tiles);
+
+        let reread = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+        for i in original.chunks() {
+            if i.kind == ChunkType::Tiles {
+                continue
+            }
+
+            assert_eq!(reread.get(i.kind, i.bank), Some(i), "{}: {} bank {} changed", name, i.kind, i.bank);
+        }
+    }
+}
+
+#[test]
+fn every_bank_round_trips() {
+    let mut chunks : Vec<Chunk> = vec![];
+
+    for bank in 0..8 {
+        chunks.push(Chunk::new(ChunkType::Tiles, bank, vec![bank; 4]));
+        chunks.push(Chunk::new(ChunkType::Code, bank, format!("-- bank {}", bank).into_bytes()));
+    }
+
+    let reread = parse_tic(&write_tic(&chunks).unwrap()).unwrap();
+
+    assert!(reread == chunks, "{}", chunk_diff(&chunks, &reread));
+}
+
+#[test]
+fn sheets_are_kept_per_bank() {
+    let mut cart = Cartridge::from_chunks(vec![
+        Chunk::new(ChunkType::Tiles, 0, vec![0x11; 32]),
+        Chunk::new(ChunkType::Tiles, 3, vec![0x33; 32]),
+    ]);
+
+    assert_eq!(cart.tiles()[0][0], 1);
+    assert_eq!(cart.tiles_in(3)[0][0], 3);
+    assert!(cart.tiles_in(5).is_empty() && cart.sprites_in(3).is_empty());
+
+    // editing bank 3 leaves bank 0 alone and keeps the bank bits
+
+    let mut sheet = cart.tiles_in(3);
+    sheet[0][0] = 7;
+
+    cart.set_tiles_in(3, &sheet);
+    cart.set_sprites_in(6, &sheet);
+
+    let reread = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(reread.tiles()[0][0], 1);
+    assert_eq!(reread.tiles_in(3), sheet);
+    assert_eq!(reread.sprites_in(6), sheet);
+    assert_eq!(reread.banks(ChunkType::Tiles), vec![0, 3]);
+}
+
+#[test]
+fn trimmed_sheets_stay_trimmed() {
+    let mut data = vec![0x11; 33];
+    data[32] = 0x05;
+
+    let mut cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Tiles, 0, data.clone())]);
+
+    let tiles = cart.tiles();
+    assert_eq!(tiles.len(), 2);
+
+    cart.set_tiles(&tiles);
+
+    assert_eq!(cart.get(ChunkType::Tiles, 0).unwrap().data, data);
+
+    // a blank sheet leaves no chunk behind
+
+    cart.set_tiles(&[vec![0; 64]]);
+
+    assert!(!cart.has(ChunkType::Tiles));
+}
+
+#[test]
+fn maps_and_flags_are_kept_per_bank() {
+    let mut cart = Cartridge::from_chunks(vec![
+        Chunk::new(ChunkType::Map, 0, vec![1, 2]),
+        Chunk::new(ChunkType::Flags, 0, vec![4]),
+    ]);
+
+    let mut map = cart.map_in(3);
+    let mut flags = cart.flags_in(3);
+
+    assert_eq!(map.get(0, 0), 0);
+    assert!(!flags.get(0, 2));
+
+    map.set(0, 0, 9);
+    flags.set(0, 2, true);
+
+    cart.set_map_in(3, &map);
+    cart.set_flags_in(3, &flags);
+
+    let reread = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(reread.map().get(0, 0), 1);
+    assert_eq!(reread.map_in(3), map);
+    assert_eq!(reread.flags().to_chunk(), vec![4]);
+    assert_eq!(reread.flags_in(3), flags);
+    assert_eq!(reread.banks(ChunkType::Map), vec![0, 3]);
+}
+
+#[test]
+fn every_chunk_id_round_trips() {
+    let chunks : Vec<Chunk> = (0..=20).map(|id| Chunk::new(ChunkType::from_id(id), 0, vec![id, 0xAA, id])).collect();
+
+    for i in &chunks {
+        assert_eq!(i.kind.id(), i.data[0]);
+    }
+
+    let reread = parse_tic(&write_tic(&chunks).unwrap()).unwrap();
+
+    assert!(reread == chunks, "{}", chunk_diff(&chunks, &reread));
+}
+
+#[test]
+fn compressed_code_is_readable_and_lossless() {
+    let source = "function TIC()\n cls(13)\nend\n";
+    let zipped = zip(source.as_bytes());
+
+    let bytes = write_tic(&[Chunk::new(ChunkType::CodeZip, 0, zipped)]).unwrap();
+    let mut cart = Cartridge::from_bytes(&bytes).unwrap();
+
+    assert_eq!(cart.code().unwrap(), source);
+    assert_eq!(cart.to_bytes().unwrap(), bytes);
+
+    cart.set_code("-- edited").unwrap();
+
+    assert!(!cart.has(ChunkType::Code));
+    assert_eq!(cart.code().unwrap(), "-- edited");
+}
+
+#[test]
+fn full_code_bank_is_stored_as_size_zero() {
+    let chunks = vec![
+        Chunk::new(ChunkType::Default, 0, vec![]),
+        Chunk::new(ChunkType::Code, 1, vec![b'-'; BANK_SIZE]),
+        Chunk::new(ChunkType::Sprites, 0, vec![]),
+    ];
+
+    let bytes = write_tic(&chunks).unwrap();
+
+    assert_eq!(&bytes[4..8], &[ChunkType::Code.id() | (1 << 5), 0, 0, 0]);
+
+    let reread = parse_tic(&bytes).unwrap();
+
+    assert!(reread == chunks, "{}", chunk_diff(&chunks, &reread));
+}
+
+#[test]
+fn unrepresentable_sizes_are_refused() {
+    let too_big = Chunk::new(ChunkType::Map, 0, vec![0; BANK_SIZE]);
+    let empty_code = Chunk::new(ChunkType::Code, 0, vec![]);
+
+    assert!(matches!(write_tic(&[too_big]), Err(CartError::BadSize(ChunkType::Map, 0, BANK_SIZE))));
+    assert!(matches!(write_tic(&[empty_code]), Err(CartError::BadSize(ChunkType::Code, 0, 0))));
+}
+
+#[test]
+fn malformed_carts_are_reported() {
+    let good = write_tic(&[Chunk::new(ChunkType::Map, 2, vec![1, 2, 3])]).unwrap();
+
+    let mut short_header = good.clone();
+    short_header.extend_from_slice(&[ChunkType::Tiles.id(), 4]);
+
+    let mut overrun = good.clone();
+    overrun.extend_from_slice(&[ChunkType::Tiles.id(), 10, 0, 0, 1, 2]);
+
+    let mut unknown = good.clone();
+    unknown.extend_from_slice(&[25, 0, 0, 0]);
+
+    assert!(matches!(parse_tic(&short_header), Err(CartError::TruncatedHeader { offset : 7, left : 2 })));
+    assert!(matches!(parse_tic(&overrun), Err(CartError::Overrun { offset : 7, kind : ChunkType::Tiles, size : 10, left : 2 })));
+    assert!(matches!(parse_tic(&unknown), Err(CartError::UnknownId { offset : 7, id : 25 })));
+}
+
+#[test]
+fn reader_points_into_the_file() {
+    let bytes = fs::read(&bundled_carts()[0]).unwrap();
+
+    let mut expected = 0;
+
+    for i in ChunkReader::new(&bytes) {
+        let i = i.unwrap();
+
+        assert_eq!(i.offset, expected);
+        assert_eq!(i.data, &bytes[i.offset + HEADER_SIZE..i.offset + HEADER_SIZE + i.data.len()]);
+
+        expected = i.offset + HEADER_SIZE + i.data.len();
+    }
+
+    assert_eq!(expected, bytes.len());
+}
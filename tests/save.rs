@@ -0,0 +1,49 @@
+use std::{fs, path::PathBuf};
+
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("artic_save_{}_{}", std::process::id(), name))
+}
+
+fn cart_with_code(code: &str) -> Cartridge {
+    Cartridge::from_chunks(vec![Chunk::new(ChunkType::Code, 0, code.as_bytes().to_vec())])
+}
+
+#[test]
+fn smaller_cart_leaves_no_stale_bytes() {
+    let path = temp_path("shrink.tic");
+    let p = path.to_str().unwrap();
+
+    let big = cart_with_code("-- a fairly long comment that makes this cart bigger");
+    let small = cart_with_code("-- tiny");
+
+    big.save(p).unwrap();
+    small.save(p).unwrap();
+
+    let written = fs::read(&path).unwrap();
+    let _ = fs::remove_file(&path);
+
+    assert_eq!(written, small.to_bytes().unwrap());
+}
+
+#[test]
+fn backup_keeps_previous_version() {
+    let path = temp_path("backup.tic");
+    let p = path.to_str().unwrap();
+    let bak = temp_path("backup.tic.bak");
+
+    let old = cart_with_code("-- old");
+    let new = cart_with_code("-- new");
+
+    old.save(p).unwrap();
+    new.save_with_backup(p).unwrap();
+
+    let written = fs::read(&path).unwrap();
+    let backup = fs::read(&bak).unwrap();
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(&bak);
+
+    assert_eq!(written, new.to_bytes().unwrap());
+    assert_eq!(backup, old.to_bytes().unwrap());
+}
@@ -0,0 +1,61 @@
+use artic_editor2::bitmap::Bitmap;
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+use artic_editor2::map::Map;
+use artic_editor2::screen::{SCREEN_H, SCREEN_W, Screen};
+
+const PALETTE : [(u8, u8, u8); 3] = [(0, 0, 0), (255, 0, 0), (0, 0, 255)];
+
+#[test]
+fn screen_round_trips_through_the_chunk() {
+    let data = vec![0x21, 0, 0x0f];
+    let cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Screen, 0, data.clone())]);
+
+    let screen = cart.screen();
+
+    assert_eq!(screen.pixels.len(), SCREEN_W * SCREEN_H);
+    assert_eq!(screen.pixels[..6], [1, 2, 0, 0, 15, 0]);
+    assert_eq!(screen.to_chunk(), data);
+
+    let img = Screen { pixels : vec![2; SCREEN_W * SCREEN_H] }.render(&PALETTE);
+
+    assert_eq!((img.width, img.height), (SCREEN_W as u32, SCREEN_H as u32));
+    assert_eq!(img.pixel(239, 135), (0, 0, 255, 255));
+}
+
+#[test]
+fn pictures_are_fitted_and_quantised() {
+    let mut img = Bitmap::new(48, 34, (250, 10, 10, 255));
+
+    for y in 17..34 {
+        for x in 0..48 {
+            img.set_pixel(x, y, (0, 0, 200, 255));
+        }
+    }
+
+    let screen = Screen::from_bitmap(&img, &PALETTE, false);
+
+    assert_eq!(screen.pixels.len(), SCREEN_W * SCREEN_H);
+    assert_eq!(screen.pixels[0], 1);
+    assert_eq!(screen.pixels[SCREEN_W * SCREEN_H - 1], 2);
+}
+
+#[test]
+fn map_capture_uses_tile_indices() {
+    let mut tiles = vec![vec![0; 64]; 2];
+    tiles[1][9] = 7;
+
+    let mut map = Map::default();
+    map.set(31, 1, 1);
+
+    let screen = Screen::from_map(&map, &tiles, 30, 0);
+
+    // tile (1, 1) of the view, pixel (1, 1) inside it
+
+    assert_eq!(screen.pixels[9 + 9 * SCREEN_W], 7);
+    assert_eq!(screen.pixels.iter().filter(|&&p| p != 0).count(), 1);
+
+    let mut cart = Cartridge::new();
+    cart.set_screen(&Screen::default());
+
+    assert!(!cart.has(ChunkType::Screen));
+}
@@ -0,0 +1,55 @@
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+use artic_editor2::sfx::{Envelope, SFX_BYTES, SFX_COUNT, SFX_TICKS, Sfx, Sounds};
+
+#[test]
+fn every_bit_survives_decoding() {
+    let raw : Vec<u8> = (0..SFX_BYTES).map(|i| (i * 37 + 11) as u8).collect();
+    let sfx = Sfx::decode(&raw);
+
+    assert_eq!(sfx.ticks.len(), SFX_TICKS);
+    assert_eq!(sfx.encode(), raw);
+
+    // octave 4, pitch16x, speed -1 (3 bit signed), reverse
+
+    let sfx = Sfx::decode(&[[0; 60].as_slice(), &[0b1111_1100, 0b1110_0101, 0x52, 0, 0, 0]].concat());
+
+    assert_eq!((sfx.octave, sfx.pitch16x, sfx.speed, sfx.reverse), (4, true, -1, true));
+    assert_eq!((sfx.note, sfx.stereo_left, sfx.stereo_right, sfx.spare), (5, false, true, 3));
+    assert_eq!((sfx.loops[0].start, sfx.loops[0].size), (2, 5));
+}
+
+#[test]
+fn envelopes_are_clamped() {
+    let mut sfx = Sfx::default();
+
+    sfx.set(Envelope::Pitch, 3, -20);
+    sfx.set(Envelope::Wave, 3, 20);
+
+    assert_eq!(sfx.get(Envelope::Pitch, 3), -8);
+    assert_eq!(sfx.get(Envelope::Wave, 3), 15);
+    assert_eq!(Sfx::decode(&sfx.encode()), sfx);
+}
+
+#[test]
+fn editing_one_sfx_keeps_the_others() {
+    let data : Vec<u8> = (0..SFX_BYTES * 3).map(|i| (i % 251) as u8 | 1).collect();
+    let mut cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Samples, 0, data.clone())]);
+
+    let mut sounds = cart.sounds();
+
+    assert_eq!(sounds.sfx.len(), SFX_COUNT);
+
+    sounds.sfx[1].set(Envelope::Volume, 0, 9);
+    cart.set_sounds(&sounds);
+
+    let after = &cart.get(ChunkType::Samples, 0).unwrap().data;
+
+    assert_eq!(after.len(), data.len());
+    assert_eq!(after[..SFX_BYTES], data[..SFX_BYTES]);
+    assert_eq!(after[SFX_BYTES * 2..], data[SFX_BYTES * 2..]);
+    assert_eq!(after[SFX_BYTES] & 15, 9);
+
+    cart.set_sounds(&Sounds::default());
+
+    assert!(!cart.has(ChunkType::Samples));
+}
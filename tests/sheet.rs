@@ -0,0 +1,41 @@
+use artic_editor2::sheet::{flood_fill, join, shift, slice};
+
+#[test]
+fn join_undoes_slice() {
+    let pixels : Vec<u8> = (0..16 * 16).map(|i| (i % 16) as u8).collect();
+    let (blocks, cols) = slice(&pixels, 16, 16);
+
+    assert_eq!(cols, 2);
+    assert_eq!(join(&blocks, cols), pixels);
+}
+
+#[test]
+fn fill_stops_at_other_colours() {
+    let mut blocks = vec![vec![0; 64], vec![0; 64]];
+
+    // a wall down the middle of the first block
+
+    for y in 0..8 {
+        blocks[0][4 + y * 8] = 3;
+    }
+
+    let mut pixels = join(&blocks, 2);
+
+    flood_fill(&mut pixels, 16, 15, 7, 9);
+
+    let filled = slice(&pixels, 16, 8).0;
+
+    assert_eq!(filled[1], vec![9; 64]);
+    assert_eq!(filled[0][3], 0);
+    assert_eq!(filled[0][4], 3);
+    assert_eq!(filled[0][5], 9);
+}
+
+#[test]
+fn shift_wraps_round() {
+    let pixels = vec![1, 2, 3, 4, 5, 6];
+
+    assert_eq!(shift(&pixels, 3, 1, 0), vec![3, 1, 2, 6, 4, 5]);
+    assert_eq!(shift(&pixels, 3, 0, -1), vec![4, 5, 6, 1, 2, 3]);
+    assert_eq!(shift(&shift(&pixels, 3, -2, 1), 3, 2, -1), pixels);
+}
@@ -0,0 +1,106 @@
+use artic_editor2::music::{FRAME_COUNT, Music, NOTE_FIRST, PATTERN_ROWS, Patterns, Row, TRACK_BYTES};
+use artic_editor2::sfx::{Envelope, Loop, SFX_TICKS, Sounds};
+use artic_editor2::synth::{loop_pos, note_freq, render_sfx, render_track, sfx_length, sfx_pos};
+use artic_editor2::wave::{Preset, Waves, preset};
+
+fn square_waves() -> Waves {
+    let mut waves = Waves::default();
+    waves.waves[0] = preset(Preset::Square, 0);
+
+    waves
+}
+
+#[test]
+fn timing_follows_tic80() {
+    assert!((note_freq(57) - 440.0).abs() < 0.01);
+    assert!((note_freq(45) - 220.0).abs() < 0.01);
+
+    assert_eq!(sfx_pos(0, 10), 10);
+    assert_eq!(sfx_pos(2, 10), 30);
+    assert_eq!(sfx_pos(-1, 10), 5);
+
+    // loop over ticks 4..6
+
+    let l = Loop { start : 4, size : 2 };
+
+    assert_eq!((0..9).map(|p| loop_pos(l, p)).collect::<Vec<usize>>(), vec![0, 1, 2, 3, 4, 5, 4, 5, 4]);
+    assert_eq!(loop_pos(Loop::default(), 100), SFX_TICKS - 1);
+}
+
+#[test]
+fn sfx_renders_for_one_pass() {
+    let waves = square_waves();
+    let mut sounds = Sounds::default();
+
+    sounds.sfx[1].speed = -1;
+
+    assert_eq!(sfx_length(&sounds.sfx[0]), SFX_TICKS);
+    assert_eq!(sfx_length(&sounds.sfx[1]), SFX_TICKS * 2);
+
+    // volume 0 is full volume, so this is a loud square
+
+    let out = render_sfx(&waves, &sounds, 0, None, 6000);
+
+    assert_eq!(out.len(), SFX_TICKS * 100);
+    assert!(out.iter().any(|&s| s > 8000) && out.iter().any(|&s| s < -8000));
+
+    // silenced by the volume envelope
+
+    for t in 0..SFX_TICKS {
+        sounds.sfx[2].set(Envelope::Volume, t, 15);
+    }
+
+    let out = render_sfx(&waves, &sounds, 2, Some(0.5), 6000);
+
+    assert_eq!(out.len(), 3000);
+    assert!(out.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn tracks_play_until_the_last_frame() {
+    let waves = square_waves();
+    let sounds = Sounds::default();
+    let mut patterns = Patterns::default();
+    let mut music = Music::default();
+
+    patterns.patterns[0][0] = Row { note : NOTE_FIRST, octave : 4, ..Row::default() };
+    music.tracks[0].frames[1] = [1, 0, 0, 0];
+    music.tracks[0].rows = 16;
+
+    // 10 rows a second at tempo 150 speed 6, 2 frames of 16 rows
+
+    let out = render_track(&waves, &sounds, &patterns, &music, 0, 6000);
+
+    assert_eq!(out.len(), 6000 * 32 / 10);
+
+    // frame 0 is empty so the first half is silent
+
+    assert!(out[..9600].iter().all(|&s| s == 0));
+    assert!(out[9600..].iter().any(|&s| s != 0));
+
+    assert!(render_track(&waves, &sounds, &patterns, &music, 1, 6000).is_empty());
+}
+
+#[test]
+fn out_of_range_rows_play_as_whole_patterns() {
+    let waves = square_waves();
+    let sounds = Sounds::default();
+    let mut patterns = Patterns::default();
+
+    patterns.patterns[0][PATTERN_ROWS - 1] = Row { note : NOTE_FIRST, octave : 4, ..Row::default() };
+
+    // rows byte 0xff wraps round to 65 rows, one more than a pattern has
+
+    let mut raw = vec![0; TRACK_BYTES];
+    raw[0] = 1;
+    raw[FRAME_COUNT * 3 + 1] = 0xff;
+
+    let music = Music::from_chunk(&raw);
+
+    assert_eq!(music.tracks[0].rows, PATTERN_ROWS + 1);
+
+    let out = render_track(&waves, &sounds, &patterns, &music, 0, 6000);
+
+    assert_eq!(out.len(), 6000 * PATTERN_ROWS / 10);
+    assert!(out.iter().any(|&s| s != 0));
+}
@@ -0,0 +1,84 @@
+use std::{fs, path::PathBuf};
+
+use artic_editor2::cartridge::{Cartridge, Chunk, ChunkType};
+use artic_editor2::wav::encode_wav;
+use artic_editor2::wave::{Preset, WAVE_COUNT, WAVE_MAX, WAVE_SAMPLES, Waves, preset, preview};
+
+#[test]
+fn waves_round_trip_through_the_chunk() {
+    let data = vec![0x10, 0x32, 0, 0xff];
+    let cart = Cartridge::from_chunks(vec![Chunk::new(ChunkType::Waveform, 0, data.clone())]);
+
+    let waves = cart.waves();
+
+    assert_eq!(waves.waves.len(), WAVE_COUNT);
+    assert_eq!(waves.waves[0][..8], [0, 1, 2, 3, 0, 0, 15, 15]);
+    assert_eq!(waves.to_chunk(), data);
+
+    let mut cart = Cartridge::new();
+    cart.set_waves(&Waves::default());
+
+    assert!(!cart.has(ChunkType::Waveform));
+}
+
+#[test]
+fn presets_fit_in_four_bits() {
+    for kind in [Preset::Square, Preset::Saw, Preset::Triangle, Preset::Noise] {
+        let wave = preset(kind, 7);
+
+        assert_eq!(wave.len(), WAVE_SAMPLES);
+        assert!(wave.iter().all(|&s| s <= WAVE_MAX));
+    }
+
+    assert_eq!(preset(Preset::Square, 0)[0], WAVE_MAX);
+    assert_eq!(preset(Preset::Saw, 0)[WAVE_SAMPLES - 1], WAVE_MAX);
+    assert_eq!(preset(Preset::Noise, 3), preset(Preset::Noise, 3));
+}
+
+#[test]
+fn preview_is_a_valid_wav() {
+    let samples = preview(&preset(Preset::Square, 0), 441.0, 0.5, 44100);
+
+    assert_eq!(samples.len(), 22050);
+
+    // 100 samples per period, the first half high
+
+    assert!(samples[10] > 0 && samples[60] < 0);
+
+    let wav = encode_wav(&samples, 44100);
+
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+    assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()), 44100);
+    assert_eq!(wav.len(), 44 + samples.len() * 2);
+}
+
+#[test]
+fn default_carts_get_tic80s_waves() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src").join("test.tic");
+    let mut cart = Cartridge::from_bytes(&fs::read(path).unwrap()).unwrap();
+
+    assert!(cart.has(ChunkType::Default) && !cart.has(ChunkType::Waveform));
+
+    let mut waves = cart.waves();
+
+    assert_eq!(&waves.waves[0][..10], &[0, 0, 0, 0, 0, 0, 0, 0, 15, 15]);
+    assert_eq!(&waves.waves[1][..4], &[0, 1, 2, 3]);
+    assert_eq!(&waves.waves[1][14..18], &[14, 15, 15, 14]);
+    assert!(waves.waves[2..].iter().flatten().all(|&s| s == 0));
+
+    // editing one slot keeps the defaults in the others
+
+    waves.waves[5] = preset(Preset::Saw, 0);
+    cart.set_waves(&waves);
+
+    let reread = Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(reread.waves(), waves);
+
+    // and flattening them all has to be written down too
+
+    cart.set_waves(&Waves::default());
+
+    assert_eq!(Cartridge::from_bytes(&cart.to_bytes().unwrap()).unwrap().waves(), Waves::default());
+}